@@ -1,38 +1,120 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+#[cfg(feature = "diagnostics")]
+use alloc::collections::VecDeque;
 use alloc::format;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
 use core::fmt::Write as _;
 use embassy_time::{Duration, Instant, Timer, with_timeout};
-use embedded_io_async::{Read as AsyncRead, Write as AsyncWrite};
+use embedded_io_async::{Error as _, Read as AsyncRead, Write as AsyncWrite};
+#[cfg(target_os = "none")]
 use esp_hal::{Async, uart::Uart};
 
 use crate::dbglog::{dtu_debug, dtu_warn};
+use crate::multipart::MultipartBuilder;
+use crate::observer::AtObserver;
 use crate::parser::{
-    build_head_line, contains_at_error, contains_http_fail, contains_http_ready, contains_ok,
-    parse_http_fail_code, parse_status_code,
+    build_head_lines, contains_at_error, contains_http_fail, contains_http_ready,
+    contains_http_timeout, contains_ok, ends_with_terminal_line, parse_cclk, parse_cops_operator,
+    parse_creg_state, parse_csq_rssi, parse_digit_token, parse_extended_error,
+    parse_http_fail_code, parse_info_line, parse_query_value, parse_status_code,
+    redact_authorization, redact_authorization_bytes, rssi_to_dbm, split_trailing_urc,
+    strip_command_echo, strip_prefixed_lines,
 };
-use crate::types::{DtuAtError, DtuAtHttpConfig, HttpRequest, HttpResponse};
+#[cfg(feature = "heapless")]
+use crate::types::HttpResponseN;
+use crate::types::{
+    CertKind, ChannelConfig, DtuAtError, DtuAtHttpConfig, HttpDataType, HttpHeader, HttpMethod,
+    HttpRequest, HttpResponse, HttpResponseRef, LinkStatus, NetworkTime, RegistrationState,
+    RequestTiming, StreamedResponse, TlsVerify, body_bounds, content_length_end, response_preview,
+};
+use crate::util::{encode_query_pairs, find_subslice, parse_url, resolve_location};
 
-/// DTU 异步 HTTP 客户端（UART 驱动）。
+/// DTU 异步 HTTP 客户端。
 ///
-/// 底层固定使用 `esp_hal::uart::Uart<'d, Async>`。
-pub struct DtuAtHttpClient<'d> {
-    transport: Uart<'d, Async>,
+/// 传输层为任意实现 `embedded_io_async::{Read, Write}` 的类型，
+/// 通常为 `esp_hal::uart::Uart<'d, Async>`（见 [`DtuUartClient`]）。
+pub struct DtuAtHttpClient<T> {
+    transport: T,
     config: DtuAtHttpConfig,
+    observer: Option<Box<dyn AtObserver>>,
+    upload_progress: Option<fn(usize, usize)>,
+    /// 由 [`new_on_channel`](Self::new_on_channel) 固定的通道号。
+    fixed_channel: Option<u8>,
+    /// 会话未 `close()` 即被丢弃，模块可能仍停留在命令模式。
+    pending_restore: bool,
+    /// 被丢弃的会话已保存的通道参数，恢复时重新下发。
+    restore_channel: Option<ChannelState>,
+    timing: RequestTiming,
+    first_byte_at: Option<Instant>,
+    /// 可复用的响应缓冲，见 [`recycle_response`](Self::recycle_response)。
+    scratch: Vec<u8>,
+    /// AT 响应读取的分块缓冲（`read_chunk_size`），首次读取时分配后复用。
+    read_buf: Vec<u8>,
+    step: Option<&'static str>,
+    #[cfg(feature = "diagnostics")]
+    recent: VecDeque<(String, Vec<u8>)>,
 }
 
-impl<'d> DtuAtHttpClient<'d> {
+/// 基于 `esp_hal` 异步 UART 的客户端类型别名（仅裸机目标）。
+#[cfg(target_os = "none")]
+pub type DtuUartClient<'d> = DtuAtHttpClient<Uart<'d, Async>>;
+
+impl<T> DtuAtHttpClient<T> {
     /// 创建客户端。
     ///
     /// # 输入
-    /// - `transport`: 已初始化的异步 UART。
+    /// - `transport`: 已初始化的异步传输层（如异步 UART）。
     /// - `config`: 驱动配置。
     ///
     /// # 返回
     /// 返回可用于发送 HTTP 请求的客户端实例。
-    pub const fn new(transport: Uart<'d, Async>, config: DtuAtHttpConfig) -> Self {
-        Self { transport, config }
+    pub const fn new(transport: T, config: DtuAtHttpConfig) -> Self {
+        Self {
+            transport,
+            config,
+            observer: None,
+            upload_progress: None,
+            fixed_channel: None,
+            pending_restore: false,
+            restore_channel: None,
+            timing: RequestTiming::ZERO,
+            first_byte_at: None,
+            scratch: Vec::new(),
+            read_buf: Vec::new(),
+            step: None,
+            #[cfg(feature = "diagnostics")]
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// 以编译期固定的通道号 `CH` 创建客户端（覆盖 `config.channel`）。
+    ///
+    /// `CH` 不在 1~4 时编译失败，例如 `new_on_channel::<0>(..)` 无法通过编译。
+    /// 通道号随后固定：通过 [`config_mut`](Self::config_mut) 改动 `channel` 后，
+    /// 请求与通道相关命令返回 `InvalidConfig`，不会向模块发送任何命令。
+    ///
+    /// ```rust
+    /// use embassy_fs_mcore_f8a2m1::{DtuAtHttpClient, DtuAtHttpConfig};
+    ///
+    /// let client = DtuAtHttpClient::new_on_channel::<2>((), DtuAtHttpConfig::default());
+    /// assert_eq!(client.config().channel, 2);
+    /// ```
+    ///
+    /// ```rust,compile_fail
+    /// use embassy_fs_mcore_f8a2m1::{DtuAtHttpClient, DtuAtHttpConfig};
+    ///
+    /// let client = DtuAtHttpClient::new_on_channel::<0>((), DtuAtHttpConfig::default());
+    /// ```
+    pub const fn new_on_channel<const CH: u8>(transport: T, mut config: DtuAtHttpConfig) -> Self {
+        const { assert!(CH >= 1 && CH <= 4, "channel 必须在 1~4") };
+        config.channel = CH;
+        let mut client = Self::new(transport, config);
+        client.fixed_channel = Some(CH);
+        client
     }
 
     /// 获取当前配置（只读）。
@@ -45,16 +127,129 @@ impl<'d> DtuAtHttpClient<'d> {
         &mut self.config
     }
 
-    /// 访问底层 UART（可写）。
-    pub fn transport_mut(&mut self) -> &mut Uart<'d, Async> {
+    /// 访问底层传输层（可写）。
+    pub fn transport_mut(&mut self) -> &mut T {
         &mut self.transport
     }
 
-    /// 取回底层 UART，消费客户端实例。
-    pub fn into_transport(self) -> Uart<'d, Async> {
+    /// 取回底层传输层，消费客户端实例。
+    pub fn into_transport(self) -> T {
         self.transport
     }
 
+    /// 设置 AT 交互观察者（替换已有的观察者）。
+    pub fn set_observer(&mut self, observer: impl AtObserver + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// 移除并返回当前观察者。
+    pub fn take_observer(&mut self) -> Option<Box<dyn AtObserver>> {
+        self.observer.take()
+    }
+
+    /// 设置上传进度回调 `(已发送, 总长)`，`None` 表示移除。
+    ///
+    /// 请求体每次写入 UART 后调用一次，完成时必有一次 `(total, total)`；空请求体报告 `(0, 0)`。
+    pub fn set_upload_progress(&mut self, on_upload_progress: Option<fn(usize, usize)>) {
+        self.upload_progress = on_upload_progress;
+    }
+
+    /// 最近一次请求执行到的阶段，请求失败时即为出错的阶段；尚未发起请求时为 `None`。
+    ///
+    /// 取值：`"enter_command_mode"`、`"WKMOD"`、`"HTPTP"`、`"HTPURL"`、`"HTPHD"`、`"HTPPK"`、
+    /// `"HTPTIM"`、`"HTPDT"`、`"link_status"`、`"save_wait"`、`"send_payload"`、`"read_http"`。
+    /// 会话中参数未变化的项不会下发，也不会出现在这里。
+    pub fn last_step(&self) -> Option<&'static str> {
+        self.step
+    }
+
+    /// 最近的 AT 交互 `(命令, 响应)`，由旧到新，最多 `recent_exchanges_len` 条（需 `diagnostics` feature）。
+    ///
+    /// 响应只保留前 64 字节，读取超时的命令记录为空响应；
+    /// 开启 `redact_secrets` 时命令中的凭据已脱敏，可直接上报或写入 flash。
+    #[cfg(feature = "diagnostics")]
+    pub fn recent_exchanges(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.recent
+            .iter()
+            .map(|(cmd, rsp)| (cmd.as_str(), rsp.as_slice()))
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn record_exchange(&mut self, cmd: &str, rsp: &[u8]) {
+        let cap = self.config.recent_exchanges_len;
+        if cap == 0 {
+            return;
+        }
+        while self.recent.len() >= cap {
+            self.recent.pop_front();
+        }
+        let cmd = loggable(cmd, self.config.redact_secrets).into_owned();
+        let rsp = rsp[..rsp.len().min(RECENT_RESPONSE_MAX)].to_vec();
+        self.recent.push_back((cmd, rsp));
+    }
+
+    /// 归还不再使用的响应，其缓冲留作下一次请求的响应缓冲。
+    ///
+    /// `request()` 等接口把响应直接读入客户端持有的缓冲，返回时缓冲随 [`HttpResponse::raw`]
+    /// 移交给调用方（不拷贝，也不借用客户端）；调用方用完后归还即可避免长时间运行时
+    /// 每次请求重新分配、扩容响应缓冲造成的堆碎片。不归还时下一次请求照常分配。
+    pub fn recycle_response(&mut self, resp: HttpResponse) {
+        if resp.raw.capacity() > self.scratch.capacity() {
+            self.scratch = resp.raw;
+        }
+    }
+
+    /// 取出 HTTP 响应缓冲（清空、保留容量，至少预留 `response_capacity_hint`）；
+    /// 读取时按需增长，不超过 `max_response_len`。
+    fn take_scratch(&mut self) -> Vec<u8> {
+        let mut buf = core::mem::take(&mut self.scratch);
+        buf.clear();
+        buf.reserve(self.response_capacity());
+        buf
+    }
+
+    /// 是否有会话（命令模式守卫）未 `close()` 即被丢弃（模块可能仍在命令模式）。
+    ///
+    /// 下一次进入命令模式时会自动恢复（确认命令模式并重新下发会话已保存的通道参数）；
+    /// 只使用透传通道时可调用 [`restore_transparent_mode`](Self::restore_transparent_mode) 主动恢复。
+    pub fn needs_restore(&self) -> bool {
+        self.pending_restore
+    }
+
+    fn report_upload(&self, sent: usize, total: usize) {
+        if let Some(on_upload_progress) = self.upload_progress {
+            on_upload_progress(sent, total);
+        }
+    }
+
+    fn observe(&mut self, f: impl FnOnce(&mut dyn AtObserver)) {
+        if let Some(observer) = self.observer.as_deref_mut() {
+            f(observer);
+        }
+    }
+
+    fn log_response_preview(&self, tag: &'static str, buf: &[u8]) {
+        log_response_preview(tag, buf, self.config.redact_secrets);
+    }
+
+    /// 按 `Content-Length` 判定的响应结束位置；开启调试 URC 时 body 中可能夹杂调试行，
+    /// 字节数不可靠，返回 `None` 退回空闲判定。
+    fn content_length_end(&self, raw: &[u8]) -> Option<usize> {
+        if self.config.enable_modem_debug_urc {
+            return None;
+        }
+        content_length_end(raw)
+    }
+
+    /// HTTP 响应 / 就绪等待缓冲的初始容量：`response_capacity_hint`，不超过 `max_response_len`。
+    fn response_capacity(&self) -> usize {
+        self.config
+            .response_capacity_hint
+            .min(self.config.max_response_len)
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite> DtuAtHttpClient<T> {
     /// 最简发送接口。
     ///
     /// # 输入
@@ -66,6 +261,8 @@ impl<'d> DtuAtHttpClient<'d> {
     /// # 返回
     /// - `Ok(HttpResponse)`: 请求完成并得到响应（可通过 `status_code` 判断 HTTP 结果）。
     /// - `Err(DtuAtError)`: 发送/等待/解析过程中出错。
+    ///
+    /// 响应用完后应交给 [`recycle_response`](Self::recycle_response)，见 [`request`](Self::request)。
     pub async fn send(
         &mut self,
         method: crate::types::HttpMethod,
@@ -93,6 +290,74 @@ impl<'d> DtuAtHttpClient<'d> {
             .await
     }
 
+    /// 类型化 JSON POST 接口（需 `serde` feature）。
+    ///
+    /// 使用 `serde-json-core` 将 `value` 序列化到堆缓冲（按需倍增，上限 64 KiB）后调用 [`post_json`](Self::post_json)；
+    /// 序列化失败或超出上限返回 `Serde`。
+    #[cfg(feature = "serde")]
+    pub async fn post_json_value<V: serde::Serialize + ?Sized>(
+        &mut self,
+        url: &str,
+        value: &V,
+    ) -> Result<HttpResponse, DtuAtError> {
+        let body = serialize_json(value)?;
+        self.post_json(url, &body).await
+    }
+
+    /// 表单 POST 接口（`application/x-www-form-urlencoded`）。
+    ///
+    /// 键与值按查询参数相同的规则百分号编码，以 `&` 连接后作为请求体，
+    /// 并自动追加 `Content-Type: application/x-www-form-urlencoded`。
+    pub async fn post_form(
+        &mut self,
+        url: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<HttpResponse, DtuAtError> {
+        let mut body = String::new();
+        encode_query_pairs(fields, &mut body);
+        let headers = [crate::types::HttpHeader::new(
+            "Content-Type",
+            "application/x-www-form-urlencoded",
+        )];
+        self.send(
+            crate::types::HttpMethod::Post,
+            url,
+            &headers,
+            body.as_bytes(),
+        )
+        .await
+    }
+
+    /// multipart/form-data POST 接口。
+    ///
+    /// 由 [`MultipartBuilder`] 生成请求体与 `Content-Type`（含 boundary）后发送。
+    /// 请求体整体驻留内存，大小同样受模块透传能力限制。
+    pub async fn post_multipart(
+        &mut self,
+        url: &str,
+        builder: &MultipartBuilder<'_>,
+    ) -> Result<HttpResponse, DtuAtError> {
+        let (content_type, body) = builder.build();
+        let headers = [crate::types::HttpHeader::new("Content-Type", &content_type)];
+        self.send(crate::types::HttpMethod::Post, url, &headers, &body)
+            .await
+    }
+
+    /// 同 [`request`](Self::request)，同时返回分阶段耗时。
+    ///
+    /// 各阶段耗时取自最后一次尝试（重试 / 重定向时），`total_ms` 覆盖整个调用，
+    /// 因此各阶段之和不超过 `total_ms`。计时只使用 `Instant::now()`，无额外分配。
+    pub async fn request_timed(
+        &mut self,
+        req: &HttpRequest<'_>,
+    ) -> Result<(HttpResponse, RequestTiming), DtuAtError> {
+        self.timing = RequestTiming::ZERO;
+        let start = Instant::now();
+        let resp = self.request(req).await?;
+        self.timing.total_ms = elapsed_ms(start, Instant::now());
+        Ok((resp, self.timing))
+    }
+
     /// 完整请求接口（带请求级重试）。
     ///
     /// # 输入
@@ -103,23 +368,204 @@ impl<'d> DtuAtHttpClient<'d> {
     ///
     /// # 错误
     /// 返回 [`DtuAtError`]，例如超时、AT 拒绝、响应格式不合法等。
-    /// 在 `max_request_attempts` 次全部失败后才返回错误。
+    /// 每次尝试都会重新进入命令模式；按 `retry_on` 策略判定是否重试（默认仅
+    /// `Timeout` / `AtRejected` / `BadResponse`），`max_request_attempts` 次全部失败后原样返回最后一次错误。
+    ///
+    /// # 响应缓冲
+    /// 响应读入客户端持有的缓冲（按需增长，上限 `max_response_len`），返回时随
+    /// [`HttpResponse::raw`] 移交给调用方，客户端无法自动收回。长时间运行的设备应在用完响应后
+    /// 调用 [`recycle_response`](Self::recycle_response) 归还，否则每次请求都会重新分配、扩容缓冲，
+    /// 造成堆碎片。`send()`、`post_*()`、`request_timed()`、`request_streaming()` 与
+    /// [`CommandSession::request_on_session`] 同理；不需要堆分配时改用 [`request_into`](Self::request_into)。
+    ///
+    /// 开启 `follow_redirects` 时，3xx 且带 `Location` 的响应会自动跳转（303 改为无 body 的 GET，
+    /// 其余保持原方法与 body），超过 `max_redirects` 跳仍是重定向时返回 `TooManyRedirects`。
+    /// 每一跳都是一次完整请求（重新进入命令模式、重新配置通道），耗时按跳数线性增加。
+    ///
+    /// 设置 `total_request_timeout` 时整个调用（含重试、重定向）受该预算约束，
+    /// 超出即返回 `Timeout`，优先于各阶段超时；中途放弃时模块可能停留在任意状态，
+    /// 下一次请求会重新进入命令模式。
     pub async fn request(&mut self, req: &HttpRequest<'_>) -> Result<HttpResponse, DtuAtError> {
+        match self.config.total_request_timeout {
+            Some(budget) => with_timeout(budget, self.request_following(req))
+                .await
+                .map_err(|_| {
+                    dtu_warn!(
+                        "dtu_http total_request_timeout exceeded ({}ms)",
+                        budget.as_millis()
+                    );
+                    DtuAtError::Timeout
+                })?,
+            None => self.request_following(req).await,
+        }
+    }
+
+    /// `request()` 主体：按配置跟随重定向。
+    async fn request_following(
+        &mut self,
+        req: &HttpRequest<'_>,
+    ) -> Result<HttpResponse, DtuAtError> {
+        if !self.config.follow_redirects {
+            return self.request_single(req).await;
+        }
+
+        let mut method = req.method;
+        let mut body = req.body;
+        let mut query_payload = req.query_payload;
+        let mut url: Option<String> = None;
+        let mut hops = 0u8;
+
+        loop {
+            let hop = match &url {
+                // 跳转目标已是完整 URL，不再追加查询参数。
+                Some(url) => HttpRequest {
+                    method,
+                    url,
+                    body,
+                    query: &[],
+                    query_payload,
+                    ..*req
+                },
+                None => *req,
+            };
+            let resp = self.request_single(&hop).await?;
+            if !resp.is_redirect() {
+                return Ok(resp);
+            }
+            let Some(location) = resp.location() else {
+                return Ok(resp);
+            };
+            if hops >= self.config.max_redirects {
+                dtu_warn!(
+                    "dtu_http too many redirects (max={})",
+                    self.config.max_redirects
+                );
+                return Err(DtuAtError::TooManyRedirects);
+            }
+
+            hops += 1;
+            let next = resolve_location(&effective_url(&hop, &self.config), &location);
+            dtu_debug!(
+                "dtu_http redirect {} -> {} (hop={})",
+                resp.status_code.unwrap_or(0),
+                next.as_str(),
+                hops
+            );
+            if resp.status_code == Some(303) {
+                method = HttpMethod::Get;
+                body = &[];
+                query_payload = &[];
+            }
+            url = Some(next);
+        }
+    }
+
+    /// 固定容量请求接口（需 `heapless` feature）：响应读入 `heapless::Vec<u8, N>`，
+    /// 不为响应分配堆内存，`N` 取代 `max_response_len` 作为响应长度上限。
+    ///
+    /// 行为与 [`request_into`](Self::request_into) 相同（含重试，不跟随重定向）；
+    /// 响应超过 `N` 字节返回 `ResponseTooLarge`，`N` 为 0 返回 `InvalidConfig`。
+    /// 缓冲随返回值按值移动，`N` 较大时注意任务栈占用。
+    #[cfg(feature = "heapless")]
+    pub async fn request_heapless<const N: usize>(
+        &mut self,
+        req: &HttpRequest<'_>,
+    ) -> Result<HttpResponseN<N>, DtuAtError> {
+        let mut raw: heapless::Vec<u8, N> = heapless::Vec::new();
+        // 容量恰为 N，填满不会失败
+        let _ = raw.resize_default(N);
+        let (status_code, len) = {
+            let resp = self.request_into(req, &mut raw).await?;
+            (resp.status_code, resp.raw.len())
+        };
+        raw.truncate(len);
+        Ok(HttpResponseN {
+            status_code,
+            raw,
+            markers: self.config.markers,
+        })
+    }
+
+    /// 单次请求（不跟随重定向）：响应读入按需增长的缓冲（上限 `max_response_len`），
+    /// 成功时缓冲移入返回的 `HttpResponse`，失败时留给下一次请求。
+    async fn request_single(&mut self, req: &HttpRequest<'_>) -> Result<HttpResponse, DtuAtError> {
+        self.validate_request(req)?;
+        let mut buf = self.take_scratch();
+        let max = self.config.max_response_len;
+        let len = match self
+            .request_with_retry(req, &mut ResponseBuf::Growable(&mut buf, max))
+            .await
+        {
+            Ok(len) => len,
+            Err(e) => {
+                self.scratch = buf;
+                return Err(e);
+            }
+        };
+        buf.truncate(len);
+        Ok(HttpResponse {
+            status_code: parse_status_code(&buf, &self.config.markers),
+            raw: buf,
+            markers: self.config.markers,
+        })
+    }
+
+    /// 无堆分配的请求接口：响应直接读入调用方提供的缓冲。
+    ///
+    /// # 输入
+    /// - `req`: 完整请求结构。
+    /// - `buf`: 响应缓冲；其长度即本次允许的最大响应长度（取代 `max_response_len`）。
+    ///
+    /// # 成功
+    /// 返回借用 `buf` 的 [`HttpResponseRef`]。
+    ///
+    /// # 错误
+    /// 与 [`request`](Self::request) 相同；响应超出 `buf` 时返回 `ResponseTooLarge`。
+    pub async fn request_into<'b>(
+        &mut self,
+        req: &HttpRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<HttpResponseRef<'b>, DtuAtError> {
         self.validate_request(req)?;
+        if buf.is_empty() {
+            return Err(DtuAtError::InvalidConfig("响应缓冲不能为空"));
+        }
+
+        let len = self
+            .request_with_retry(req, &mut ResponseBuf::Fixed(&mut *buf))
+            .await?;
+        let raw = &buf[..len];
+        Ok(HttpResponseRef {
+            status_code: parse_status_code(raw, &self.config.markers),
+            raw,
+            markers: self.config.markers,
+        })
+    }
 
+    /// 按 `max_request_attempts` / `retry_on` 重试整个请求流程，返回响应长度。
+    async fn request_with_retry(
+        &mut self,
+        req: &HttpRequest<'_>,
+        buf: &mut ResponseBuf<'_>,
+    ) -> Result<usize, DtuAtError> {
         let max = self.config.max_request_attempts.max(1);
         let mut last_err = DtuAtError::Timeout;
 
         for attempt in 1..=max {
             if attempt > 1 {
+                let delay = retry_backoff(&self.config, attempt - 1);
                 dtu_warn!(
                     "dtu_http retrying request (attempt={}/{})",
                     attempt,
                     max
                 );
+                if delay.as_ticks() > 0 {
+                    dtu_debug!("dtu_http retry backoff={}ms", delay.as_millis());
+                    Timer::after(delay).await;
+                }
             }
-            match self.request_inner(req).await {
-                Ok(resp) => return Ok(resp),
+            match self.request_inner(req, buf).await {
+                Ok(len) => return Ok(len),
                 Err(e) => {
                     dtu_warn!(
                         "dtu_http request attempt={}/{} failed: {}",
@@ -128,13 +574,8 @@ impl<'d> DtuAtHttpClient<'d> {
                         e.as_str()
                     );
                     last_err = e;
-                    // Transport 错误和配置错误不重试
-                    if matches!(
-                        e,
-                        DtuAtError::Transport(_)
-                            | DtuAtError::InvalidConfig(_)
-                            | DtuAtError::ResponseTooLarge
-                    ) {
+                    // 按 retry_on 策略判定（默认仅超时 / AT 拒绝 / 响应异常重试）
+                    if !self.config.retry_on.should_retry(&e) {
                         return Err(e);
                     }
                 }
@@ -144,637 +585,4099 @@ impl<'d> DtuAtHttpClient<'d> {
         Err(last_err)
     }
 
-    /// 单次请求执行体（不含重试，由 `request()` 调用）。
-    async fn request_inner(&mut self, req: &HttpRequest<'_>) -> Result<HttpResponse, DtuAtError> {
-        dtu_debug!(
-            "dtu_http request start, ch={}, method={}, url={}",
-            self.config.channel,
-            req.method.as_at(),
-            req.url
-        );
+    /// 流式上传接口：请求体从 `body` 读取，按 `read_chunk_size` 分块写入 UART，不要求整体驻留内存。
+    ///
+    /// `body_len` 为请求体总长度；`req.headers` 未包含 `Content-Length` 时自动追加。
+    /// `req.body` 被忽略。由于读取器只能消费一次，本接口不做请求级重试与 payload 重发。
+    ///
+    /// DTU 按串口空闲分包，读取器应能连续供数，否则请求体可能被模块提前截断发送。
+    /// `body` 提前结束时返回 `InvalidConfig`，读取错误映射为 `Transport`。
+    /// 响应用完后应交给 [`recycle_response`](Self::recycle_response)，见 [`request`](Self::request)。
+    pub async fn request_streaming<R: AsyncRead>(
+        &mut self,
+        req: &HttpRequest<'_>,
+        body: &mut R,
+        body_len: usize,
+    ) -> Result<HttpResponse, DtuAtError> {
+        self.validate_request(req)?;
 
-        self.enter_command_mode().await.map_err(|e| {
-            dtu_warn!("dtu_http step=enter_command_mode failed: {}", e.as_str());
-            e
-        })?;
-        self.send_ok_cmd(&format!("AT+WKMOD{}=HTTP", self.config.channel))
-            .await
-            .map_err(|e| {
-                dtu_warn!("dtu_http step=WKMOD failed: {}", e.as_str());
-                e
-            })?;
-        self.send_ok_cmd(&format!(
-            "AT+HTPTP{}={}",
-            self.config.channel,
-            req.method.as_at()
-        ))
-        .await
-        .map_err(|e| {
-            dtu_warn!("dtu_http step=HTPTP failed: {}", e.as_str());
-            e
-        })?;
-        self.send_ok_cmd(&format!("AT+HTPURL{}={}", self.config.channel, req.url))
+        let len_value = format!("{}", body_len);
+        let mut headers = req.headers.to_vec();
+        if !headers
+            .iter()
+            .any(|h| h.name.eq_ignore_ascii_case("Content-Length"))
+        {
+            headers.push(HttpHeader::new("Content-Length", &len_value));
+        }
+        let req = HttpRequest {
+            headers: &headers,
+            body: &[],
+            ..*req
+        };
+
+        self.prepare_http_channel(&req).await?;
+        self.send_payload_from(body, body_len).await?;
+
+        let mut buf = self.take_scratch();
+        let max = self.config.max_response_len;
+        let len = match self
+            .read_http_response(&req, &mut ResponseBuf::Growable(&mut buf, max), None)
             .await
-            .map_err(|e| {
-                dtu_warn!("dtu_http step=HTPURL failed: {}", e.as_str());
-                e
-            })?;
+        {
+            Ok(len) => len,
+            Err(e) => {
+                self.scratch = buf;
+                return Err(e);
+            }
+        };
+        buf.truncate(len);
+        Ok(HttpResponse {
+            status_code: parse_status_code(&buf, &self.config.markers),
+            raw: buf,
+            markers: self.config.markers,
+        })
+    }
+
+    /// 流式下载接口：仅将 HTTP body 逐块写入 `sink`，不在内存中累积，因此 body 不受
+    /// `max_response_len` 限制（响应头仍需在该上限内）。
+    ///
+    /// 响应头缓冲至头/体边界后解析状态码；声明 `Content-Length` 时恰好转发该长度，
+    /// 否则丢弃 body 之后的 URC 行（如 `FS@HTTP ...`）。chunked 分块格式原样转发；
+    /// 开启 `enable_modem_debug_urc` 时混入 body 的调试行不作过滤。
+    /// 收到完整响应头前读取结束时，按 [`HttpResponse::http_body`] 的规则提取 body 后写入。
+    /// 由于数据已交给 `sink`，本接口不做请求级重试与 payload 重发，也不检查 body 缺失。
+    pub async fn request_to_writer<W: AsyncWrite>(
+        &mut self,
+        req: &HttpRequest<'_>,
+        sink: &mut W,
+    ) -> Result<StreamedResponse, DtuAtError> {
+        self.validate_request(req)?;
+        self.prepare_http_channel(req).await?;
+        self.send_payload(&req.payload()).await?;
+
+        self.step = Some("read_http");
+        let first_timeout = req
+            .http_first_timeout
+            .unwrap_or(self.config.http_first_timeout);
+        let urc_prefix = self.config.markers.urc_prefix;
+        let debug_prefix = (self.config.enable_modem_debug_urc
+            && !self.config.debug_urc_prefix.is_empty())
+        .then_some(self.config.debug_urc_prefix);
+        let mut chunk = vec![0u8; self.config.read_chunk_size.max(MIN_READ_CHUNK_SIZE)];
+        let mut head: Vec<u8> = Vec::new();
+        // 找到头/体边界后为 Some(声明的 Content-Length)
+        let mut body_len: Option<Option<usize>> = None;
+        // 未声明长度时暂缓转发的 body 尾部（可能是结尾 URC）
+        let mut held: Vec<u8> = Vec::new();
+        let mut body_done = false;
+        let mut received = 0usize;
+        let mut total_len = 0usize;
+        let mut got_payload = false;
+        let mut followup_deadline: Option<Instant> = None;
 
-        let head_line =
-            build_head_line(req.headers, req.bearer_token).map_err(DtuAtError::InvalidConfig)?;
-        dtu_debug!("dtu_http headers prepared, len={}", head_line.len());
-        if !head_line.is_empty() {
-            self.send_ok_cmd(&format!("AT+HTPHD{}={}", self.config.channel, head_line))
+        loop {
+            // 与 collect_followup_http_data 一致：只收到 URC 时继续短轮询等待 HTTP 数据
+            let timeout = if received == 0 {
+                first_timeout
+            } else if got_payload {
+                self.config.http_idle_timeout
+            } else {
+                short_poll_timeout(self.config.http_followup_first_timeout)
+            };
+
+            let n = match with_timeout(timeout, AsyncRead::read(&mut self.transport, &mut chunk))
                 .await
-                .map_err(|e| {
-                    dtu_warn!("dtu_http step=HTPHD failed: {}", e.as_str());
-                    e
-                })?;
-        }
+            {
+                Ok(result) => result.map_err(|e| DtuAtError::Transport(e.kind()))?,
+                Err(_) if received == 0 => {
+                    dtu_warn!("dtu_http step=stream_response first byte timeout");
+                    return Err(DtuAtError::Timeout);
+                }
+                Err(_) if got_payload => break,
+                Err(_) => {
+                    let deadline = *followup_deadline
+                        .get_or_insert_with(|| Instant::now() + self.config.http_followup_timeout);
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            if n == 0 {
+                break;
+            }
 
-        self.send_ok_cmd(&format!(
-            "AT+HTPPK{}={}",
-            self.config.channel, self.config.response_filter_mask
-        ))
-        .await
-        .map_err(|e| {
-            dtu_warn!("dtu_http step=HTPPK failed: {}", e.as_str());
-            e
-        })?;
-        self.send_ok_cmd(&format!(
-            "AT+HTPTIM{}={}",
-            self.config.channel, self.config.request_timeout_secs
-        ))
-        .await
-        .map_err(|e| {
-            dtu_warn!("dtu_http step=HTPTIM failed: {}", e.as_str());
-            e
-        })?;
-        self.send_ok_cmd(&format!(
-            "AT+HTPDT{}={}",
-            self.config.channel,
-            req.data_type.as_at()
-        ))
-        .await
-        .map_err(|e| {
-            dtu_warn!("dtu_http step=HTPDT failed: {}", e.as_str());
-            e
-        })?;
+            received += n;
+            let data = &chunk[..n];
+            if !data.starts_with(urc_prefix) {
+                got_payload = true;
+            }
+            self.observe(|o| o.on_response(data));
+            if body_done {
+                continue;
+            }
 
-        if self.config.enable_modem_debug_urc {
-            if let Err(e) = self.send_ok_cmd("AT+DEBUG=ON").await {
-                dtu_warn!("dtu_http step=DEBUG_ON failed (continue): {}", e.as_str());
+            let fresh = if body_len.is_some() {
+                data
+            } else {
+                head.extend_from_slice(data);
+                if contains_http_fail(&head, &self.config.markers) {
+                    let code = parse_http_fail_code(&head, &self.config.markers);
+                    dtu_warn!("dtu_http FS@HTTP FAIL:{} (TLS/connection error)", code);
+                    return Err(DtuAtError::HttpFail(code));
+                }
+                if contains_http_timeout(&head, &self.config.markers) {
+                    dtu_warn!("dtu_http FS@HTTP TIMEOUT (modem-side)");
+                    return Err(DtuAtError::ModemTimeout);
+                }
+                let Some((body_start, content_len)) = body_bounds(&head) else {
+                    if head.len() > self.config.max_response_len {
+                        dtu_warn!(
+                            "dtu_http step=stream_response header exceeds max_response_len={}",
+                            self.config.max_response_len
+                        );
+                        return Err(DtuAtError::ResponseTooLarge);
+                    }
+                    continue;
+                };
+                // 与 content_length_end 一致：调试行可能混入 body，长度不可靠
+                body_len = Some(content_len.filter(|_| !self.config.enable_modem_debug_urc));
+                &head[body_start..]
+            };
+
+            if let Some(Some(content_len)) = body_len {
+                let take = fresh.len().min(content_len - total_len);
+                AsyncWrite::write_all(sink, &fresh[..take])
+                    .await
+                    .map_err(|e| DtuAtError::Transport(e.kind()))?;
+                total_len += take;
+                if total_len >= content_len {
+                    dtu_debug!("dtu_http stream reached Content-Length={}", content_len);
+                    break;
+                }
+            } else {
+                held.extend_from_slice(fresh);
+                let (safe, end) =
+                    split_trailing_urc(&held, total_len == 0, urc_prefix, debug_prefix);
+                AsyncWrite::write_all(sink, &held[..safe])
+                    .await
+                    .map_err(|e| DtuAtError::Transport(e.kind()))?;
+                total_len += safe;
+                if end {
+                    held.clear();
+                    body_done = true;
+                } else {
+                    held.drain(..safe);
+                }
             }
         }
 
-        if self.config.query_link_status_before_send {
-            self.try_log_link_status().await;
+        let body: &[u8] = match body_len {
+            Some(_) => &held,
+            None => HttpResponseRef {
+                status_code: None,
+                raw: &head,
+                markers: self.config.markers,
+            }
+            .http_body()
+            .unwrap_or(&[]),
+        };
+        if !body.is_empty() {
+            AsyncWrite::write_all(sink, body)
+                .await
+                .map_err(|e| DtuAtError::Transport(e.kind()))?;
+            total_len += body.len();
         }
 
-        self.send_save_and_wait_http_ready().await.map_err(|e| {
+        self.log_response_preview("http_stream", &head);
+        dtu_debug!(
+            "dtu_http stream finished, received={} body_len={}",
+            received,
+            total_len
+        );
+        Ok(StreamedResponse {
+            status_code: parse_status_code(&head, &self.config.markers),
+            total_len,
+        })
+    }
+
+    /// 开启连续请求会话：进入一次命令模式，后续请求参数不变时免去 `+++` 与 `AT+S`。
+    ///
+    /// 详见 [`CommandSession`]。
+    pub async fn session(&mut self) -> Result<CommandSession<'_, T>, DtuAtError> {
+        self.enter_command_mode().await.map_err(|e| {
             dtu_warn!(
-                "dtu_http step=save_reboot_wait_ready failed: {}",
+                "dtu_http step=session enter_command_mode failed: {}",
                 e.as_str()
             );
             e
         })?;
+        Ok(CommandSession {
+            client: self,
+            applied: None,
+            in_command_mode: true,
+            dirty: false,
+        })
+    }
 
-        dtu_debug!(
-            "dtu_http wait post_ready_settle={}ms",
-            self.config.post_entm_settle_time.as_millis()
-        );
-        Timer::after(self.config.post_entm_settle_time).await;
-
-        self.send_payload(req.body).await?;
+    /// 同 [`session`](Self::session)：进入命令模式并返回作为守卫的 [`CommandSession`]，
+    /// 期间可发送任意命令，结束时应调用 [`CommandSession::close`] 回到透传模式。
+    pub async fn enter_command_mode_guard(&mut self) -> Result<CommandSession<'_, T>, DtuAtError> {
+        self.session().await
+    }
 
-        let raw = match self
-            .read_until_idle(
-                self.config.http_first_timeout,
-                self.config.http_idle_timeout,
-            )
-            .await
-        {
-            Ok(raw) => raw,
-            Err(DtuAtError::Timeout) if self.config.retry_payload_on_http_timeout => {
-                dtu_warn!("dtu_http step=read_http_response timeout, retry payload once");
-                self.send_payload(req.body).await?;
-                self.read_until_idle(
-                    self.config.http_first_timeout,
-                    self.config.http_idle_timeout,
-                )
-                .await
-                .map_err(|e| {
-                    dtu_warn!(
-                        "dtu_http step=read_http_response_retry failed: {}",
-                        e.as_str()
-                    );
-                    e
-                })?
-            }
-            Err(e) => {
-                dtu_warn!("dtu_http step=read_http_response failed: {}", e.as_str());
-                return Err(e);
-            }
-        };
-
-        let raw = self.collect_followup_http_data(raw).await?;
-        log_response_preview("http", &raw);
-
-        // 检测 DTU 固件级 HTTP 失败（FS@HTTP FAIL:N），通常为 TLS 握手失败或连接错误。
-        if contains_http_fail(&raw) {
-            let code = parse_http_fail_code(&raw);
-            dtu_warn!("dtu_http FS@HTTP FAIL:{} (TLS/connection error)", code);
-            return Err(DtuAtError::HttpFail(code));
+    /// 若此前有会话未 `close()` 即被丢弃，重新进入命令模式、重新下发其已保存的通道参数并回到透传模式
+    /// （有通道参数时 `AT+S`，否则 `AT+ENTM`），然后清除标记；否则什么也不做。
+    pub async fn restore_transparent_mode(&mut self) -> Result<(), DtuAtError> {
+        if !self.pending_restore {
+            return Ok(());
         }
-
-        let resp = HttpResponse {
-            status_code: parse_status_code(&raw),
-            raw,
+        let reapply = self.restore_channel.is_some();
+        self.enter_command_mode().await?;
+        let result = if reapply {
+            self.start_http_mode().await
+        } else {
+            self.exit_command_mode().await
         };
-
-        let allow_empty_body = matches!(resp.status_code, Some(204 | 304));
-
-        if self.config.require_body_on_success && resp.is_success() && !allow_empty_body {
-            let body_missing = match resp.http_body() {
-                Some(body) => body.is_empty(),
-                None => true,
-            };
-
-            if body_missing {
-                if let Some(content_len) = resp.declared_content_length() {
-                    dtu_warn!(
-                        "dtu_http success but body missing, declared content-length={}",
-                        content_len
-                    );
-                } else {
-                    dtu_warn!("dtu_http success but body missing (no content-length found)");
-                }
-                return Err(DtuAtError::BodyMissing);
-            }
+        if result.is_err() {
+            // 仍在命令模式，下一次进入命令模式时再确认
+            self.pending_restore = true;
         }
+        result
+    }
 
-        Ok(resp)
+    /// 退出命令模式，回到数据透传模式（`AT+ENTM`）。
+    ///
+    /// 适用于 UART 同时承载透传数据的场景：请求完成后主动交还透传通道。
+    /// 之后再次发起请求时，`enter_command_mode` 会在 `cmd_guard_time` 静默后
+    /// 发送 `+++` 重新进入命令模式，因此两次调用之间无需额外等待。
+    pub async fn exit_command_mode(&mut self) -> Result<(), DtuAtError> {
+        self.send_ok_cmd("AT+ENTM").await.map_err(|e| {
+            dtu_warn!("dtu_http step=ENTM failed: {}", e.as_str());
+            e
+        })
     }
 
-    async fn collect_followup_http_data(
+    /// 持续读取 UART，直到累计数据中出现 `marker`（可跨多次读取），返回累计的全部字节。
+    ///
+    /// 不发送任何命令，也不切换模式，适合等待 `+CME ERROR` 或自定义 URC。
+    /// `timeout` 内未出现返回 `Timeout`；累计超过 `max_response_len` 返回 `ResponseTooLarge`；
+    /// `marker` 为空返回 `InvalidConfig`。
+    pub async fn wait_for_marker(
         &mut self,
-        mut raw: Vec<u8>,
+        marker: &[u8],
+        timeout: Duration,
     ) -> Result<Vec<u8>, DtuAtError> {
-        let deadline = Instant::now() + self.config.http_followup_timeout;
-        let mut timeout_streak = 0u8;
-        let mut appended = false;
-        let mut got_non_urc_payload = false;
-
-        while Instant::now() < deadline {
-            let poll_first_timeout = short_poll_timeout(self.config.http_followup_first_timeout);
-            let chunk = match self
-                .read_until_idle_quiet(poll_first_timeout, self.config.http_idle_timeout)
-                .await
-            {
-                Ok(c) => c,
-                Err(DtuAtError::Timeout) => {
-                    timeout_streak = timeout_streak.saturating_add(1);
-                    if timeout_streak >= 1 && (appended || got_non_urc_payload) {
-                        break;
-                    }
-                    continue;
-                }
-                Err(e) => return Err(e),
-            };
-
-            if chunk.is_empty() {
-                timeout_streak = timeout_streak.saturating_add(1);
-                if timeout_streak >= 1 && (appended || got_non_urc_payload) {
-                    break;
-                }
-                continue;
-            }
-
-            timeout_streak = 0;
-            appended = true;
-            let non_urc = !chunk.starts_with(b"FS@");
-            if non_urc {
-                got_non_urc_payload = true;
-            }
-            log_response_preview("http_followup", &chunk);
-
-            if raw.len() + chunk.len() > self.config.max_response_len {
-                return Err(DtuAtError::ResponseTooLarge);
-            }
-            raw.extend_from_slice(&chunk);
-
-            if non_urc {
-                break;
-            }
-        }
-
-        Ok(raw)
-    }
-
-    async fn send_payload(&mut self, payload: &[u8]) -> Result<(), DtuAtError> {
-        dtu_debug!("dtu_http payload bytes={}", payload.len());
-        if payload.is_empty() {
-            self.write_all(b" ").await.map_err(|e| {
-                dtu_warn!("dtu_http step=send_payload(empty) failed: {}", e.as_str());
-                e
-            })
-        } else {
-            self.write_all(payload).await.map_err(|e| {
-                dtu_warn!("dtu_http step=send_payload failed: {}", e.as_str());
-                e
-            })
+        if marker.is_empty() {
+            return Err(DtuAtError::InvalidConfig("marker 不能为空"));
         }
-    }
-
-    async fn send_save_and_wait_http_ready(&mut self) -> Result<(), DtuAtError> {
-        dtu_debug!("dtu_http >> AT+S");
-        self.write_all(b"AT+S").await?;
-        self.write_all(b"\r\n").await?;
 
-        let deadline = Instant::now() + self.config.http_ready_timeout;
+        let deadline = Instant::now() + timeout;
         let mut merged = Vec::new();
 
-        while Instant::now() < deadline {
-            let poll_first_timeout = short_poll_timeout(self.config.at_first_timeout);
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                dtu_warn!("dtu_http wait_for_marker timeout");
+                self.log_response_preview("wait_marker_final", &merged);
+                return Err(DtuAtError::Timeout);
+            }
+            let poll_first_timeout =
+                short_poll_timeout(self.config.at_first_timeout).min(deadline - now);
             let chunk = match self
                 .read_until_idle_quiet(poll_first_timeout, self.config.at_idle_timeout)
                 .await
             {
                 Ok(c) => c,
-                Err(DtuAtError::Timeout) => {
-                    continue;
-                }
+                Err(DtuAtError::Timeout) => continue,
                 Err(e) => return Err(e),
             };
-
             if chunk.is_empty() {
                 continue;
             }
 
-            log_response_preview("save_wait", &chunk);
-
-            if merged.len() + chunk.len() <= self.config.max_response_len {
-                merged.extend_from_slice(&chunk);
-            }
-
-            if contains_at_error(&merged) {
-                return Err(DtuAtError::AtRejected);
-            }
-            if contains_http_fail(&merged) {
-                return Err(DtuAtError::BadResponse);
+            self.log_response_preview("wait_marker", &chunk);
+            self.observe(|o| o.on_response(&chunk));
+            if merged.len() + chunk.len() > self.config.max_response_len {
+                return Err(DtuAtError::ResponseTooLarge);
             }
-            if contains_http_ready(&merged) {
-                dtu_debug!("dtu_http got FS@HTTP OK, ready for payload");
-                return Ok(());
+            // 只需从可能跨块的位置开始查找
+            let search_from = merged.len().saturating_sub(marker.len() - 1);
+            merged.extend_from_slice(&chunk);
+            if find_subslice(&merged[search_from..], marker).is_some() {
+                return Ok(merged);
             }
         }
+    }
 
-        dtu_warn!("dtu_http wait FS@HTTP OK timeout");
-        log_response_preview("save_wait_final", &merged);
-        Err(DtuAtError::Timeout)
+    /// 按驱动内部相同的空闲判定读取一段响应：`first_timeout` 内等待首字节，
+    /// 之后任意两次读取间隔超过 `idle_timeout` 即视为结束，返回累计的全部字节。
+    ///
+    /// 不发送任何命令，也不切换模式；首字节超时返回 `Timeout`，
+    /// 累计超过 `max_response_len` 返回 `ResponseTooLarge`。适合读取命令之后延迟到达的应答：
+    ///
+    /// ```rust,ignore
+    /// let rsp = client.send_command("AT+CSQ").await?;
+    /// // 部分固件先回 OK，结果行随后才到
+    /// let late = client
+    ///     .read_response(Duration::from_secs(2), Duration::from_millis(100))
+    ///     .await?;
+    /// ```
+    pub async fn read_response(
+        &mut self,
+        first_timeout: Duration,
+        idle_timeout: Duration,
+    ) -> Result<Vec<u8>, DtuAtError> {
+        let rsp = self.read_until_idle(first_timeout, idle_timeout).await?;
+        self.log_response_preview("read_response", &rsp);
+        self.observe(|o| o.on_response(&rsp));
+        Ok(rsp)
     }
 
-    async fn try_log_link_status(&mut self) {
-        match self.send_query_cmd("AT+CREG?").await {
-            Ok(rsp) => log_response_preview("creg", &rsp),
-            Err(e) => dtu_warn!("dtu_http link query CREG failed: {}", e.as_str()),
-        }
+    /// 发送任意 AT 命令（自动追加 `line_terminator`），原样返回模块响应，不解析 `OK` / `ERROR`。
+    ///
+    /// 每次调用都会先确认命令模式：发送一次 `AT` 探测，已在命令模式时直接发送命令
+    /// （多一次 `AT` 往返），否则等待 `cmd_guard_time` 后发送 `command_mode_sequence` 重新进入。
+    /// 调用结束后停留在命令模式；命令本身若改变模块状态（如 `AT+ENTM`、`AT+S`），
+    /// 后续状态由调用方负责。需要连续发送多条命令而不重复探测时使用
+    /// [`CommandSession::send_command`]。命令中不能包含 CR / LF。
+    pub async fn send_command(&mut self, cmd: &str) -> Result<Vec<u8>, DtuAtError> {
+        validate_command(cmd)?;
+        self.enter_command_mode().await?;
+        self.exchange_raw_cmd(cmd, "raw_cmd").await.map_err(|e| {
+            self.observe(|o| o.on_error(&e));
+            e
+        })
+    }
 
-        match self.send_query_cmd("AT+RUNST?").await {
-            Ok(rsp) => log_response_preview("runst", &rsp),
-            Err(e) => dtu_warn!("dtu_http link query RUNST failed: {}", e.as_str()),
-        }
+    /// 同 [`send_command`](Self::send_command)，但要求响应包含 `OK`：
+    /// 收到 `ERROR` 返回 `AtRejected`，未见 `OK` 返回 `BadResponse`。
+    pub async fn send_command_expect_ok(&mut self, cmd: &str) -> Result<(), DtuAtError> {
+        validate_command(cmd)?;
+        self.enter_command_mode().await?;
+        self.send_ok_cmd(cmd).await
     }
 
-    fn validate_request(&self, req: &HttpRequest<'_>) -> Result<(), DtuAtError> {
-        if !(1..=4).contains(&self.config.channel) {
-            return Err(DtuAtError::InvalidConfig("channel 必须在 1~4"));
-        }
-        if req.url.is_empty() {
-            return Err(DtuAtError::InvalidConfig("url 不能为空"));
+    /// 依次发送一组 AT 命令，每条都要求响应包含 `OK`，遇到第一个失败即停止（如批量下发配置）。
+    ///
+    /// 只进入一次命令模式，结束后停留在命令模式（同 [`send_command`](Self::send_command)）。
+    /// 失败时返回 `(序号, 错误)`，序号从 0 开始；命令格式不合法时不发送任何命令，
+    /// 进入命令模式失败时序号为 0。
+    pub async fn run_sequence(&mut self, cmds: &[&str]) -> Result<(), (usize, DtuAtError)> {
+        for (idx, cmd) in cmds.iter().enumerate() {
+            validate_command(cmd).map_err(|e| (idx, e))?;
         }
-        if self.config.max_response_len == 0 {
-            return Err(DtuAtError::InvalidConfig("max_response_len 不能为 0"));
+        self.enter_command_mode().await.map_err(|e| (0, e))?;
+        for (idx, cmd) in cmds.iter().enumerate() {
+            self.send_ok_cmd(cmd).await.map_err(|e| {
+                dtu_warn!("dtu_http sequence step {} failed: {}", idx, e.as_str());
+                (idx, e)
+            })?;
         }
         Ok(())
     }
 
-    /// 进入 DTU 命令模式（状态机实现，覆盖所有 DTU 状态）。
+    /// 重启 DTU（`AT+Z`），等待模块重新回到命令模式。
     ///
-    /// 处理场景：
-    /// - DTU 已在命令模式（AT+S 重启后）→ Step 1 AT 探测直接成功
-    /// - DTU 在数据/HTTP 透传模式 → Step 2 `+++` 进入命令模式
-    /// - DTU 正在重启（AT+S 触发）→ Step 3 循环等待直到 `enter_cmd_timeout`
-    async fn enter_command_mode(&mut self) -> Result<(), DtuAtError> {
-        let deadline = Instant::now() + self.config.enter_cmd_timeout;
-
-        // Step 1: 丢弃 UART 残留数据，然后快速 AT 探测。
-        // 如果 DTU 已在命令模式（如 AT+S 后重启回命令模式），直接返回。
-        self.drain_uart().await;
-        match self.quick_at_probe().await {
-            Ok(()) => {
-                dtu_debug!("dtu_http enter_cmd: already in command mode (AT probe)");
-                return Ok(());
-            }
-            // ERROR: DTU 有响应但在非命令模式（数据模式把 AT\r\n 当 payload 转发）
-            // Timeout / BadResponse: DTU 不在命令模式或处于重启中
-            // 以上均继续走 +++ 流程
-            Err(DtuAtError::AtRejected | DtuAtError::BadResponse | DtuAtError::Timeout) => {}
-            Err(e) => return Err(e),
-        }
+    /// 重启期间 UART 可能完全静默或输出启动信息，均视为正常；
+    /// 之后循环发送 `AT` 探测，在 `enter_cmd_timeout` 内收到 `OK` 即返回 `Ok(())`。
+    pub async fn reboot(&mut self) -> Result<(), DtuAtError> {
+        self.enter_command_mode().await.map_err(|e| {
+            dtu_warn!(
+                "dtu_http step=reboot enter_command_mode failed: {}",
+                e.as_str()
+            );
+            e
+        })?;
 
-        // Step 2: guard time + `+++`，从数据/HTTP 透传模式进入命令模式。
-        dtu_debug!("dtu_http enter command mode via +++");
-        Timer::after(self.config.cmd_guard_time).await;
-        self.write_all(b"+++").await?;
-        // +++ 后同样需要静默窗口，让 DTU 识别转义序列
-        Timer::after(Duration::from_millis(300)).await;
+        dtu_debug!("dtu_http >> AT+Z");
+        self.observe(|o| o.on_command("AT+Z"));
+        self.write_command("AT+Z").await?;
+        let deadline = Instant::now() + self.config.enter_cmd_timeout;
 
+        // 模块可能在回复 OK 前就已复位，因此这里的超时不算失败。
         match self
             .read_until_idle_quiet(self.config.at_first_timeout, self.config.at_idle_timeout)
             .await
         {
             Ok(rsp) => {
-                log_response_preview("enter_cmd", &rsp);
-
-                if contains_ok(&rsp) {
-                    return Ok(());
-                }
-
-                if contains_at_error(&rsp) {
-                    // +++ 被 DTU 当成 AT 命令处理→ 已在命令模式，drain 并用 AT 确认
-                    dtu_warn!("dtu_http +++ got ERROR (already in cmd mode), drain + confirm");
-                    self.drain_uart().await;
-                    return self.quick_at_probe().await;
+                self.log_response_preview("reboot", &rsp);
+                self.observe(|o| o.on_response(&rsp));
+                if contains_at_error(&rsp, self.config.error_tokens) {
+                    return Err(DtuAtError::AtRejected);
                 }
-
-                // 收到 URC 等其他内容，进入等待循环
-                dtu_warn!("dtu_http +++ got unexpected data, entering wait loop");
-                self.wait_for_command_mode(deadline).await
             }
             Err(DtuAtError::Timeout) => {
-                // +++ 无响应：DTU 可能正在重启（AT+S 触发），循环等待
-                dtu_warn!("dtu_http +++ timeout, DTU may be rebooting, wait for command mode...");
-                self.wait_for_command_mode(deadline).await
+                dtu_debug!("dtu_http reboot: no reply, DTU restarting");
             }
-            Err(e) => Err(e),
+            Err(e) => return Err(e),
         }
+
+        self.wait_for_command_mode(deadline).await.map_err(|e| {
+            dtu_warn!("dtu_http step=reboot wait_cmd failed: {}", e.as_str());
+            e
+        })
     }
 
-    /// 单次 `AT\r\n` 快速探测（不重试）。
+    /// 允许模块进入低功耗睡眠（`AT+CSCLK=1`）。
     ///
-    /// - `Ok(())`: DTU 在命令模式且响应正常
-    /// - `Err(AtRejected)`: 收到 ERROR（DTU 在数据模式时出现）
-    /// - `Err(Timeout)`: 无响应
-    /// - `Err(BadResponse)`: 收到数据但没有 OK
-    async fn quick_at_probe(&mut self) -> Result<(), DtuAtError> {
-        dtu_debug!("dtu_http >> AT (probe)");
-        self.write_all(b"AT\r\n").await?;
+    /// 之后模块在 UART 空闲一段时间后自动睡眠（部分型号还要求 DTR 拉高），睡眠期间不处理串口数据，
+    /// 唤醒时收到的首批字节可能丢失。开启 RTS/CTS 硬件流控时模块睡眠期间可能拉低 CTS，
+    /// 主机侧写入会一直阻塞，本驱动的写操作没有超时，调用方应自行用 `with_timeout` 包裹或先拉低 DTR。
+    /// 再次通信前调用 [`Self::wake`]；不再需要睡眠时调用 [`Self::disable_sleep`]。
+    pub async fn enter_sleep(&mut self) -> Result<(), DtuAtError> {
+        self.enter_command_mode().await?;
+        self.send_ok_cmd("AT+CSCLK=1").await.map_err(|e| {
+            dtu_warn!("dtu_http step=CSCLK failed: {}", e.as_str());
+            e
+        })
+    }
 
-        match self
-            .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
-            .await
-        {
-            Ok(rsp) => {
-                log_response_preview("at_probe", &rsp);
-                if contains_at_error(&rsp) {
-                    return Err(DtuAtError::AtRejected);
-                }
-                if contains_ok(&rsp) {
-                    return Ok(());
-                }
-                Err(DtuAtError::BadResponse)
-            }
-            Err(e) => Err(e),
-        }
+    /// 唤醒模块：循环发送 `AT` 直到收到 `OK`（超过 `enter_cmd_timeout` 返回 `Timeout`）。
+    ///
+    /// 唤醒前的 `AT` 可能被模块当作唤醒信号丢弃，因此首次探测不回复属正常现象。
+    /// 开启 RTS/CTS 硬件流控时，模块拉高 CTS 之前探测写入会阻塞（见 [`Self::enter_sleep`]），
+    /// 应先拉低 DTR 或用 `with_timeout` 包裹。唤醒后 `AT+CSCLK=1` 仍然生效，UART 再次空闲后
+    /// 模块会重新睡眠；需要保持唤醒时随后调用 [`Self::disable_sleep`]。
+    pub async fn wake(&mut self) -> Result<(), DtuAtError> {
+        let deadline = Instant::now() + self.config.enter_cmd_timeout;
+        self.drain_uart().await;
+        self.wait_for_command_mode(deadline).await.map_err(|e| {
+            dtu_warn!("dtu_http step=wake wait_cmd failed: {}", e.as_str());
+            e
+        })
     }
 
-    /// 在 `deadline` 前循环发 `AT\r\n`，等待 DTU 从重启中恢复并进入命令模式。
-    async fn wait_for_command_mode(&mut self, deadline: Instant) -> Result<(), DtuAtError> {
-        let mut attempt = 0u32;
+    /// 关闭自动睡眠（`AT+CSCLK=0`），模块不再在 UART 空闲后睡眠。
+    pub async fn disable_sleep(&mut self) -> Result<(), DtuAtError> {
+        self.enter_command_mode().await?;
+        self.send_ok_cmd("AT+CSCLK=0").await.map_err(|e| {
+            dtu_warn!("dtu_http step=CSCLK failed: {}", e.as_str());
+            e
+        })
+    }
 
-        loop {
-            if Instant::now() >= deadline {
-                dtu_warn!(
-                    "dtu_http wait_cmd deadline exceeded (attempt={})",
-                    attempt
-                );
-                return Err(DtuAtError::Timeout);
-            }
+    /// 恢复出厂设置（`AT+RELD`），`reboot` 为 `true` 时随后调用 [`Self::reboot`] 使其生效。
+    ///
+    /// **破坏性操作**：所有通道配置（工作模式、HTTP 参数、APN 等）都会丢失。
+    /// 必须传入 `confirm = true`，否则直接返回 `InvalidConfig`，不会向模块发送任何命令。
+    pub async fn factory_reset(&mut self, confirm: bool, reboot: bool) -> Result<(), DtuAtError> {
+        if !confirm {
+            return Err(DtuAtError::InvalidConfig("恢复出厂设置需要 confirm=true"));
+        }
 
-            Timer::after(self.config.enter_cmd_poll).await;
-            attempt += 1;
-            dtu_debug!("dtu_http >> AT (wait_cmd attempt={})", attempt);
-            self.write_all(b"AT\r\n").await?;
+        self.enter_command_mode().await?;
+        self.send_ok_cmd("AT+RELD").await.map_err(|e| {
+            dtu_warn!("dtu_http step=RELD failed: {}", e.as_str());
+            e
+        })?;
 
-            match self
-                .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
-                .await
-            {
-                Ok(rsp) => {
-                    log_response_preview("wait_cmd", &rsp);
+        if reboot {
+            self.reboot().await?;
+        }
+        Ok(())
+    }
 
-                    if contains_ok(&rsp) {
-                        dtu_debug!("dtu_http wait_cmd OK (attempt={})", attempt);
+    /// 设置模块串口波特率（`AT+UART=<baud>,8,1,NONE,NONE`）。
+    ///
+    /// 只接受 9600 ~ 921600 之间的常用波特率。模块回复 `OK` 后即切换到新速率，
+    /// 调用方需随后将主机侧 UART 重新配置为相同波特率，否则后续通信全部失败。
+    /// 新速率通常需 `AT+S` 保存后才会在重启后保持。
+    pub async fn set_baud(&mut self, baud: u32) -> Result<(), DtuAtError> {
+        if !SUPPORTED_BAUD_RATES.contains(&baud) {
+            return Err(DtuAtError::InvalidConfig("不支持的波特率"));
+        }
+
+        self.enter_command_mode().await?;
+        self.send_ok_cmd(&format!("AT+UART={},8,1,NONE,NONE", baud))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=UART failed: {}", e.as_str());
+                e
+            })
+    }
+
+    /// 配置蜂窝网络 APN（`AT+CGDCONT=1,"IP","<apn>"`）。
+    ///
+    /// 提供 `user` / `pass` 任一项时，额外发送 `AT+CGAUTH=1,1,"<user>","<pass>"`（PAP 认证），
+    /// 缺省项按空串处理。APN 必须非空且不超过 63 字节；参数中不允许出现 `"`。
+    pub async fn configure_apn(
+        &mut self,
+        apn: &str,
+        user: Option<&str>,
+        pass: Option<&str>,
+    ) -> Result<(), DtuAtError> {
+        if apn.is_empty() || apn.len() > 63 {
+            return Err(DtuAtError::InvalidConfig("APN 长度必须在 1~63 字节之间"));
+        }
+        if [Some(apn), user, pass]
+            .into_iter()
+            .flatten()
+            .any(|v| v.contains('"'))
+        {
+            return Err(DtuAtError::InvalidConfig("APN 参数不能包含双引号"));
+        }
+
+        self.enter_command_mode().await?;
+        self.send_ok_cmd(&format!("AT+CGDCONT=1,\"IP\",\"{}\"", apn))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=CGDCONT failed: {}", e.as_str());
+                e
+            })?;
+
+        if user.is_some() || pass.is_some() {
+            self.send_ok_cmd(&format!(
+                "AT+CGAUTH=1,1,\"{}\",\"{}\"",
+                user.unwrap_or(""),
+                pass.unwrap_or("")
+            ))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=CGAUTH failed: {}", e.as_str());
+                e
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 设置当前通道的 HTTPS 证书校验模式（`AT+HTPSSL<ch>=<n>`）。
+    ///
+    /// `ServerCert` / `MutualAuth` 需事先用 [`Self::upload_certificate`] 写入 CA / 客户端证书；
+    /// 固件不支持该命令时模块回复 `ERROR`，返回 `AtRejected`。
+    /// 与其他通道参数一样，通常需 `AT+S` 保存后才会在重启后保持。
+    pub async fn set_tls_verify(&mut self, mode: TlsVerify) -> Result<(), DtuAtError> {
+        self.validate_channel()?;
+        self.enter_command_mode().await?;
+        dtu_debug!("dtu_http tls verify={}", mode.as_str());
+        self.send_ok_cmd(&format!(
+            "AT+HTPSSL{}={}",
+            self.config.channel,
+            mode.as_at()
+        ))
+        .await
+        .map_err(|e| {
+            dtu_warn!("dtu_http step=HTPSSL failed: {}", e.as_str());
+            e
+        })
+    }
+
+    /// 向模块证书槽 `slot` 写入 PEM 证书或私钥（`AT+SSLCERT=<slot>,<kind>,<len>` 后发送原始 PEM）。
+    ///
+    /// 模块回复 `>` 提示符时随后写入数据；部分固件不回提示符，首字节超时同样继续写入。
+    /// 命令阶段回复 `ERROR` 返回 `AtRejected`（固件不支持或槽号无效），
+    /// 数据写入后按 `send_command_expect_ok` 的规则等待 `OK`。PEM 内容不会写入日志。
+    pub async fn upload_certificate(
+        &mut self,
+        slot: u8,
+        kind: CertKind,
+        pem: &[u8],
+    ) -> Result<(), DtuAtError> {
+        if pem.is_empty() {
+            return Err(DtuAtError::InvalidConfig("证书内容不能为空"));
+        }
+
+        self.enter_command_mode().await?;
+        let cmd = format!("AT+SSLCERT={},{},{}", slot, kind.as_at(), pem.len());
+        match self.exchange_raw_cmd(&cmd, "cert_rsp").await {
+            Ok(rsp) => {
+                if let Some(e) = parse_extended_error(&rsp) {
+                    return Err(e);
+                }
+                if contains_at_error(&rsp, self.config.error_tokens) {
+                    dtu_warn!("dtu_http step=SSLCERT rejected");
+                    return Err(DtuAtError::AtRejected);
+                }
+                if !rsp.trim_ascii_end().ends_with(b">") {
+                    dtu_debug!("dtu_http SSLCERT: no prompt, sending data");
+                }
+            }
+            Err(DtuAtError::Timeout) => {
+                dtu_debug!("dtu_http SSLCERT: no reply, sending data");
+            }
+            Err(e) => return Err(e),
+        }
+
+        dtu_debug!(
+            "dtu_http cert slot={} kind={} bytes={}",
+            slot,
+            kind.as_str(),
+            pem.len()
+        );
+        self.write_body_chunked(&[], pem, 0, pem.len())
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=SSLCERT data failed: {}", e.as_str());
+                e
+            })?;
+
+        let rsp = self
+            .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
+            .await?;
+        self.log_response_preview("cert_rsp", &rsp);
+        self.observe(|o| o.on_response(&rsp));
+        self.check_ok_response(&rsp).map_err(|e| {
+            dtu_warn!("dtu_http step=SSLCERT failed: {}", e.as_str());
+            e
+        })
+    }
+
+    /// 等待模块完成网络注册（本地或漫游）。
+    ///
+    /// 每隔 `enter_cmd_poll` 发送一次 `AT+CREG?`，解析 `<stat>` 为 1 或 5 时返回 `Ok(())`；
+    /// 超过 `timeout` 仍未注册返回 `Timeout`。
+    pub async fn wait_for_registration(&mut self, timeout: Duration) -> Result<(), DtuAtError> {
+        let deadline = Instant::now() + timeout;
+        self.enter_command_mode().await?;
+
+        loop {
+            match self.send_query_cmd("AT+CREG?").await {
+                Ok(rsp) => {
+                    let state = parse_creg_state(&rsp).unwrap_or(RegistrationState::Unknown);
+                    if state.is_registered() {
+                        dtu_debug!("dtu_http registration={}", state.as_str());
                         return Ok(());
                     }
+                    dtu_debug!("dtu_http waiting registration, state={}", state.as_str());
+                }
+                Err(e @ (DtuAtError::Transport(_) | DtuAtError::WriteZero)) => return Err(e),
+                Err(e) => dtu_debug!("dtu_http CREG query failed: {}", e.as_str()),
+            }
+
+            if Instant::now() >= deadline {
+                dtu_warn!("dtu_http wait_for_registration deadline exceeded");
+                return Err(DtuAtError::Timeout);
+            }
+            Timer::after(self.config.enter_cmd_poll).await;
+        }
+    }
+
+    /// 查询信号强度（`AT+CSQ`），返回换算后的 dBm（`-113 + 2 * rssi`）。
+    ///
+    /// 会先进入命令模式；rssi 为 99（未知）或超出 0~31 时返回 `BadResponse`。
+    pub async fn query_signal_strength(&mut self) -> Result<i16, DtuAtError> {
+        self.enter_command_mode().await?;
+        let rsp = self.send_query_cmd("AT+CSQ").await?;
+        parse_csq_rssi(&rsp)
+            .and_then(rssi_to_dbm)
+            .ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 查询链路状态：注册状态（`AT+CREG?`）、信号强度（`AT+CSQ`）与运行状态（`AT+RUNST?`）。
+    ///
+    /// 会先进入命令模式；`AT+CREG?` 失败时返回错误，CSQ / RUNST 失败时对应字段为 `None`。
+    pub async fn query_link_status(&mut self) -> Result<LinkStatus, DtuAtError> {
+        self.enter_command_mode().await?;
+        self.read_link_status().await
+    }
+
+    /// 查询模块 IMEI（`AT+GSN`），返回 15 位数字串。
+    ///
+    /// 会先进入命令模式；响应中没有合法的 15 位数字 token 时返回 `BadResponse`。
+    pub async fn query_imei(&mut self) -> Result<String, DtuAtError> {
+        self.enter_command_mode().await?;
+        let rsp = self.send_query_cmd("AT+GSN").await?;
+        parse_digit_token(&rsp, 15, 15)
+            .map(String::from)
+            .ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 查询 SIM 卡 ICCID（`AT+ICCID`），返回 19~20 位数字串。
+    ///
+    /// 会先进入命令模式；响应中没有合法的数字 token 时返回 `BadResponse`。
+    pub async fn query_iccid(&mut self) -> Result<String, DtuAtError> {
+        self.enter_command_mode().await?;
+        let rsp = self.send_query_cmd("AT+ICCID").await?;
+        parse_digit_token(&rsp, 19, 20)
+            .map(String::from)
+            .ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 查询模块固件版本（`AT+VER`），返回响应中第一条非空、非 `OK` 的行。
+    ///
+    /// 会先进入命令模式；响应只有 `OK` 时返回 `BadResponse`。
+    pub async fn query_firmware_version(&mut self) -> Result<String, DtuAtError> {
+        self.enter_command_mode().await?;
+        let rsp = self.send_query_cmd("AT+VER").await?;
+        parse_info_line(&rsp, "AT+VER", self.config.ok_tokens)
+            .map(String::from)
+            .ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 查询当前注册的运营商名称（`AT+COPS?`），返回 `+COPS: <mode>,<format>,"<name>"` 中的 `<name>`。
+    ///
+    /// 会先进入命令模式；名称的形式（长名、短名或数字 PLMN）取决于模块当前的 `<format>` 设置。
+    /// 未注册（响应中没有名称字段）时返回 `BadResponse`。
+    pub async fn query_operator(&mut self) -> Result<String, DtuAtError> {
+        self.enter_command_mode().await?;
+        let rsp = self.send_query_cmd("AT+COPS?").await?;
+        parse_cops_operator(&rsp)
+            .map(String::from)
+            .ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 查询网络时间（`AT+CCLK?`），解析 `+CCLK: "yy/MM/dd,hh:mm:ss±zz"`。
+    ///
+    /// 会先进入命令模式；模块尚未从网络同步时间时通常返回出厂默认值（如 `"00/01/01,..."`），
+    /// 是否可信由调用方判断。响应无法解析时返回 `BadResponse`。
+    pub async fn query_network_time(&mut self) -> Result<NetworkTime, DtuAtError> {
+        self.enter_command_mode().await?;
+        let rsp = self.send_query_cmd("AT+CCLK?").await?;
+        parse_cclk(&rsp).ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 读回模块中已保存的当前通道 HTTP 参数（`AT+WKMOD?` / `AT+HTPTP?` / `AT+HTPURL?` 等）。
+    ///
+    /// 用于在 `AT+S` 保存后核对设置是否生效；任一查询无法解析时返回 `BadResponse`。
+    pub async fn query_channel_config(&mut self) -> Result<ChannelConfig, DtuAtError> {
+        self.validate_channel()?;
+        self.enter_command_mode().await?;
+
+        let work_mode = self.query_channel_value("WKMOD").await?;
+        let method = HttpMethod::from_at(&self.query_channel_value("HTPTP").await?)
+            .ok_or(DtuAtError::BadResponse)?;
+        let url = self.query_channel_value("HTPURL").await?;
+        let response_filter_mask = self
+            .query_channel_value("HTPPK")
+            .await?
+            .parse()
+            .map_err(|_| DtuAtError::BadResponse)?;
+        let request_timeout_secs = self
+            .query_channel_value("HTPTIM")
+            .await?
+            .parse()
+            .map_err(|_| DtuAtError::BadResponse)?;
+        let data_type = HttpDataType::from_at(&self.query_channel_value("HTPDT").await?)
+            .ok_or(DtuAtError::BadResponse)?;
+
+        Ok(ChannelConfig {
+            work_mode,
+            method,
+            url,
+            response_filter_mask,
+            request_timeout_secs,
+            data_type,
+        })
+    }
+
+    /// 发送 `AT+<name><ch>?` 并解析 `+<name><ch>:<value>` 回显。
+    async fn query_channel_value(&mut self, name: &str) -> Result<String, DtuAtError> {
+        let rsp = self
+            .send_query_cmd(&format!("AT+{}{}?", name, self.config.channel))
+            .await?;
+        parse_query_value(&rsp, name)
+            .map(String::from)
+            .ok_or(DtuAtError::BadResponse)
+    }
+
+    /// 单次请求执行体（不含重试，由 `request_into()` 调用），返回写入 `buf` 的响应长度。
+    async fn request_inner(
+        &mut self,
+        req: &HttpRequest<'_>,
+        buf: &mut ResponseBuf<'_>,
+    ) -> Result<usize, DtuAtError> {
+        self.prepare_http_channel(req).await?;
+        let payload = req.payload();
+        self.send_payload(&payload).await?;
+        self.read_http_response(req, buf, Some(&payload)).await
+    }
+
+    /// 进入命令模式并下发本次请求的全部通道参数，返回时 DTU 已就绪等待 payload。
+    async fn prepare_http_channel(&mut self, req: &HttpRequest<'_>) -> Result<(), DtuAtError> {
+        dtu_debug!(
+            "dtu_http request start, ch={}, method={}, url={}",
+            self.config.channel,
+            req.method.as_at(),
+            req.url
+        );
+
+        let state = ChannelState::new(req, &self.config)?;
+        let t0 = Instant::now();
+        self.step = Some("enter_command_mode");
+        self.enter_command_mode().await.map_err(|e| {
+            dtu_warn!("dtu_http step=enter_command_mode failed: {}", e.as_str());
+            e
+        })?;
+        let t1 = Instant::now();
+        self.apply_channel_state(None, &state).await?;
+        let t2 = Instant::now();
+        self.start_http_mode().await?;
+
+        self.timing.enter_command_mode_ms = elapsed_ms(t0, t1);
+        self.timing.config_apply_ms = elapsed_ms(t1, t2);
+        self.timing.save_wait_ms = elapsed_ms(t2, Instant::now());
+        Ok(())
+    }
+
+    /// 下发通道参数；`prev` 为已生效的参数时只发送有变化的项（`None` 表示全部下发）。
+    async fn apply_channel_state(
+        &mut self,
+        prev: Option<&ChannelState>,
+        next: &ChannelState,
+    ) -> Result<(), DtuAtError> {
+        if prev.is_none() {
+            self.step = Some("WKMOD");
+            self.send_ok_cmd(&format!("AT+WKMOD{}=HTTP", self.config.channel))
+                .await
+                .map_err(|e| {
+                    dtu_warn!("dtu_http step=WKMOD failed: {}", e.as_str());
+                    e
+                })?;
+        }
+
+        if prev.is_none_or(|p| p.method != next.method) {
+            self.step = Some("HTPTP");
+            self.send_ok_cmd(&format!(
+                "AT+HTPTP{}={}",
+                self.config.channel,
+                next.method.as_at()
+            ))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=HTPTP failed: {}", e.as_str());
+                // GET / POST 为固件文档支持的方法，仅扩展方法被拒绝时视为不支持
+                match (e, next.method) {
+                    (
+                        DtuAtError::AtRejected | DtuAtError::CmeError(_) | DtuAtError::CmsError(_),
+                        HttpMethod::Put | HttpMethod::Patch | HttpMethod::Delete | HttpMethod::Head,
+                    ) => DtuAtError::UnsupportedMethod,
+                    (e, _) => e,
+                }
+            })?;
+        }
+
+        if prev.is_none_or(|p| p.url != next.url) {
+            self.step = Some("HTPURL");
+            self.send_ok_cmd(&format!("AT+HTPURL{}={}", self.config.channel, next.url))
+                .await
+                .map_err(|e| {
+                    dtu_warn!("dtu_http step=HTPURL failed: {}", e.as_str());
+                    e
+                })?;
+        }
+
+        if prev.is_none_or(|p| p.head_lines != next.head_lines) {
+            dtu_debug!(
+                "dtu_http headers prepared, commands={}",
+                next.head_lines.len()
+            );
+            for (idx, line) in next.head_lines.iter().enumerate() {
+                self.step = Some("HTPHD");
+                self.send_ok_cmd(&format!("AT+HTPHD{}={}", self.config.channel, line))
+                    .await
+                    .map_err(|e| {
+                        dtu_warn!("dtu_http step=HTPHD({}) failed: {}", idx, e.as_str());
+                        // 后续 HTPHD 被拒绝：固件不支持追加头部，按超长处理
+                        match e {
+                            DtuAtError::AtRejected if idx > 0 => {
+                                DtuAtError::InvalidConfig("HTTP 头超过 AT+HTPHD 256 字节限制")
+                            }
+                            e => e,
+                        }
+                    })?;
+            }
+        }
+
+        if prev.is_none_or(|p| p.response_filter_mask != next.response_filter_mask) {
+            self.step = Some("HTPPK");
+            self.send_ok_cmd(&format!(
+                "AT+HTPPK{}={}",
+                self.config.channel, next.response_filter_mask
+            ))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=HTPPK failed: {}", e.as_str());
+                e
+            })?;
+        }
+
+        if prev.is_none_or(|p| p.request_timeout_secs != next.request_timeout_secs) {
+            self.step = Some("HTPTIM");
+            self.send_ok_cmd(&format!(
+                "AT+HTPTIM{}={}",
+                self.config.channel, next.request_timeout_secs
+            ))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=HTPTIM failed: {}", e.as_str());
+                e
+            })?;
+        }
+
+        if prev.is_none_or(|p| p.data_type != next.data_type) {
+            self.step = Some("HTPDT");
+            self.send_ok_cmd(&format!(
+                "AT+HTPDT{}={}",
+                self.config.channel,
+                next.data_type.as_at()
+            ))
+            .await
+            .map_err(|e| {
+                dtu_warn!("dtu_http step=HTPDT failed: {}", e.as_str());
+                e
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// 可选调试项后保存参数（`AT+S`），等待 `FS@HTTP OK` 并静置，返回时 DTU 已进入 HTTP 透传。
+    async fn start_http_mode(&mut self) -> Result<(), DtuAtError> {
+        if self.config.enable_modem_debug_urc {
+            if let Err(e) = self.send_ok_cmd("AT+DEBUG=ON").await {
+                dtu_warn!("dtu_http step=DEBUG_ON failed (continue): {}", e.as_str());
+            }
+        }
+
+        if self.config.query_link_status_before_send {
+            self.step = Some("link_status");
+            self.check_link_status().await?;
+        }
+
+        self.step = Some("save_wait");
+        self.send_save_and_wait_http_ready().await.map_err(|e| {
+            dtu_warn!(
+                "dtu_http step=save_reboot_wait_ready failed: {}",
+                e.as_str()
+            );
+            e
+        })?;
+
+        dtu_debug!(
+            "dtu_http wait post_ready_settle={}ms",
+            self.config.post_entm_settle_time.as_millis()
+        );
+        Timer::after(self.config.post_entm_settle_time).await;
+        Ok(())
+    }
+
+    /// 读取 HTTP 响应到 `buf`，处理后续分包、`FS@HTTP FAIL` 与 body 缺失检查。
+    ///
+    /// `resend` 为 `Some` 且开启 `retry_payload_on_http_timeout` 时，首字节超时后重发一次 payload。
+    async fn read_http_response(
+        &mut self,
+        req: &HttpRequest<'_>,
+        buf: &mut ResponseBuf<'_>,
+        resend: Option<&[u8]>,
+    ) -> Result<usize, DtuAtError> {
+        self.step = Some("read_http");
+        let result = self.read_http_response_inner(req, buf, resend).await;
+        if let Err(e) = &result {
+            self.observe(|o| o.on_error(e));
+        }
+        result
+    }
+
+    async fn read_http_response_inner(
+        &mut self,
+        req: &HttpRequest<'_>,
+        buf: &mut ResponseBuf<'_>,
+        resend: Option<&[u8]>,
+    ) -> Result<usize, DtuAtError> {
+        let http_first_timeout = req
+            .http_first_timeout
+            .unwrap_or(self.config.http_first_timeout);
+        let sent_at = Instant::now();
+        self.first_byte_at = None;
+        let len = match self
+            .read_until_idle_into(
+                buf,
+                0,
+                http_first_timeout,
+                self.config.http_idle_timeout,
+                true,
+            )
+            .await
+        {
+            Ok(len) => len,
+            Err(DtuAtError::Timeout)
+                if self.config.retry_payload_on_http_timeout && resend.is_some() =>
+            {
+                dtu_warn!("dtu_http step=read_http_response timeout, retry payload once");
+                self.send_payload(resend.unwrap_or_default()).await?;
+                self.read_until_idle_into(
+                    buf,
+                    0,
+                    http_first_timeout,
+                    self.config.http_idle_timeout,
+                    true,
+                )
+                .await
+                .map_err(|e| {
+                    dtu_warn!(
+                        "dtu_http step=read_http_response_retry failed: {}",
+                        e.as_str()
+                    );
+                    e
+                })?
+            }
+            Err(e) => {
+                dtu_warn!("dtu_http step=read_http_response failed: {}", e.as_str());
+                return Err(e);
+            }
+        };
+
+        self.timing.first_byte_ms = self.first_byte_at.map_or(0, |at| elapsed_ms(sent_at, at));
+
+        let len = match self.content_length_end(&buf[..len]) {
+            Some(end) if len >= end => len,
+            _ => self.collect_followup_http_data(buf, len).await?,
+        };
+        let mut len = self.strip_debug_urc(buf, len);
+        if self.config.wait_for_full_body && req.method != HttpMethod::Head {
+            len = self.collect_full_body(buf, len).await?;
+        }
+        let raw = &buf[..len];
+        self.log_response_preview("http", raw);
+        self.observe(|o| o.on_response(raw));
+
+        // 检测 DTU 固件级 HTTP 失败（FS@HTTP FAIL:N），通常为 TLS 握手失败或连接错误。
+        if contains_http_fail(raw, &self.config.markers) {
+            let code = parse_http_fail_code(raw, &self.config.markers);
+            dtu_warn!("dtu_http FS@HTTP FAIL:{} (TLS/connection error)", code);
+            return Err(DtuAtError::HttpFail(code));
+        }
+        // 模块侧等待服务器超时（FS@HTTP TIMEOUT），与主机侧读取超时区分。
+        if contains_http_timeout(raw, &self.config.markers) {
+            dtu_warn!("dtu_http FS@HTTP TIMEOUT (modem-side)");
+            return Err(DtuAtError::ModemTimeout);
+        }
+
+        let resp = HttpResponseRef {
+            status_code: parse_status_code(raw, &self.config.markers),
+            raw,
+            markers: self.config.markers,
+        };
+
+        let allow_empty_body =
+            req.method == HttpMethod::Head || matches!(resp.status_code, Some(204 | 206 | 304));
+
+        if self.config.require_body_on_success && resp.is_success() && !allow_empty_body {
+            let body_missing = match resp.http_body() {
+                Some(body) => body.is_empty(),
+                None => true,
+            };
+
+            if body_missing {
+                if let Some(content_len) = resp.declared_content_length() {
+                    dtu_warn!(
+                        "dtu_http success but body missing, declared content-length={}",
+                        content_len
+                    );
+                } else {
+                    dtu_warn!("dtu_http success but body missing (no content-length found)");
+                }
+                return Err(DtuAtError::BodyMissing);
+            }
+        }
+
+        Ok(len)
+    }
+
+    async fn collect_followup_http_data(
+        &mut self,
+        buf: &mut ResponseBuf<'_>,
+        mut len: usize,
+    ) -> Result<usize, DtuAtError> {
+        let deadline = Instant::now() + self.config.http_followup_timeout;
+        let mut timeout_streak = 0u8;
+        let mut appended = false;
+        let mut got_non_urc_payload = false;
+
+        while Instant::now() < deadline {
+            let poll_first_timeout = short_poll_timeout(self.config.http_followup_first_timeout);
+            let new_len = match self
+                .read_until_idle_into(
+                    buf,
+                    len,
+                    poll_first_timeout,
+                    self.config.http_idle_timeout,
+                    false,
+                )
+                .await
+            {
+                Ok(n) => n,
+                Err(DtuAtError::Timeout) => {
+                    timeout_streak = timeout_streak.saturating_add(1);
+                    if timeout_streak >= 1 && (appended || got_non_urc_payload) {
+                        break;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if new_len == len {
+                timeout_streak = timeout_streak.saturating_add(1);
+                if timeout_streak >= 1 && (appended || got_non_urc_payload) {
+                    break;
+                }
+                continue;
+            }
+
+            let chunk = &buf[len..new_len];
+            timeout_streak = 0;
+            appended = true;
+            let non_urc = !chunk.starts_with(self.config.markers.urc_prefix);
+            if non_urc {
+                got_non_urc_payload = true;
+            }
+            self.log_response_preview("http_followup", chunk);
+            len = new_len;
+
+            if non_urc {
+                break;
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// 开启 `enable_modem_debug_urc` 时，从 `buf[..len]` 中移除以 `debug_urc_prefix` 开头的行
+    /// （逐行写入调试日志），返回移除后的长度。
+    fn strip_debug_urc(&self, buf: &mut [u8], len: usize) -> usize {
+        let prefix = self.config.debug_urc_prefix;
+        if !self.config.enable_modem_debug_urc || prefix.is_empty() {
+            return len;
+        }
+        for line in buf[..len].split(|b| *b == b'\n') {
+            if line.starts_with(prefix) {
+                dtu_debug!(
+                    "dtu_http modem debug: {}",
+                    String::from_utf8_lossy(line).trim_end()
+                );
+            }
+        }
+        strip_prefixed_lines(&mut buf[..len], prefix)
+    }
+
+    /// 声明了 `Content-Length` 而 body 不足时继续读取，直到收齐或 `http_followup_timeout` 到期。
+    async fn collect_full_body(
+        &mut self,
+        buf: &mut ResponseBuf<'_>,
+        mut len: usize,
+    ) -> Result<usize, DtuAtError> {
+        let deadline = Instant::now() + self.config.http_followup_timeout;
+
+        loop {
+            len = self.strip_debug_urc(buf, len);
+            let resp = HttpResponseRef {
+                status_code: None,
+                raw: &buf[..len],
+                markers: self.config.markers,
+            };
+            let Some(want) = resp.declared_content_length() else {
+                return Ok(len);
+            };
+            let have = resp.http_body().map_or(0, <[u8]>::len);
+            if have >= want {
+                return Ok(len);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                dtu_warn!("dtu_http body truncated, have={} want={}", have, want);
+                return Err(DtuAtError::BodyTruncated { have, want });
+            }
+            let poll_first_timeout =
+                short_poll_timeout(self.config.http_followup_first_timeout).min(deadline - now);
+            match self
+                .read_until_idle_into(
+                    buf,
+                    len,
+                    poll_first_timeout,
+                    self.config.http_idle_timeout,
+                    false,
+                )
+                .await
+            {
+                Ok(n) => len = n,
+                Err(DtuAtError::Timeout) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn send_payload(&mut self, payload: &[u8]) -> Result<(), DtuAtError> {
+        self.step = Some("send_payload");
+        dtu_debug!("dtu_http payload bytes={}", payload.len());
+        self.observe(|o| o.on_payload(payload));
+        if payload.is_empty() {
+            self.write_all(b" ").await.map_err(|e| {
+                dtu_warn!("dtu_http step=send_payload(empty) failed: {}", e.as_str());
+                e
+            })?;
+            self.report_upload(0, 0);
+            Ok(())
+        } else {
+            self.write_body_chunked(&[], payload, 0, payload.len())
+                .await
+                .map_err(|e| {
+                    dtu_warn!("dtu_http step=send_payload failed: {}", e.as_str());
+                    e
+                })
+        }
+    }
+
+    /// 从读取器分块转发 `body_len` 字节到 UART（长度为 0 时与 `send_payload` 一样发送单个空格）。
+    async fn send_payload_from<R: AsyncRead>(
+        &mut self,
+        body: &mut R,
+        body_len: usize,
+    ) -> Result<(), DtuAtError> {
+        if body_len == 0 {
+            return self.send_payload(&[]).await;
+        }
+        self.step = Some("send_payload");
+
+        dtu_debug!("dtu_http streaming payload bytes={}", body_len);
+        let sequence = self.config.command_mode_sequence;
+        // 每次读取后保留末尾 `len - 1` 字节与下一次读取拼接，跨读取的序列也能被检查到
+        let keep = sequence.len().saturating_sub(1);
+        let mut chunk = vec![0u8; self.config.read_chunk_size.max(MIN_READ_CHUNK_SIZE) + keep];
+        let mut held = 0;
+        let mut sent = 0;
+        let mut remaining = body_len;
+        // 已发送数据的最后 `len` 字节，用于判断分块边界是否紧邻序列
+        let mut tail: Vec<u8> = Vec::new();
+
+        while remaining > 0 {
+            let want = core::cmp::min(remaining, chunk.len() - held);
+            let n = body
+                .read(&mut chunk[held..held + want])
+                .await
+                .map_err(|e| DtuAtError::Transport(e.kind()))?;
+            if n == 0 {
+                dtu_warn!(
+                    "dtu_http step=send_payload_from reader ended, missing {} bytes",
+                    remaining
+                );
+                return Err(DtuAtError::InvalidConfig(
+                    "body 读取器提前结束，少于 body_len",
+                ));
+            }
+            remaining -= n;
+            let avail = held + n;
+            let out = if remaining == 0 {
+                avail
+            } else {
+                avail.saturating_sub(keep)
+            };
+            // 首次写出时 chunk 至少含 `len` 字节（或整个请求体），最后一次含请求体的末尾 `len` 字节
+            if payload_escape_risk_at(
+                &chunk[..avail],
+                sent == 0 && out > 0,
+                remaining == 0,
+                sequence,
+            ) {
+                dtu_warn!("dtu_http streamed body starts or ends with command-mode sequence");
+                return Err(DtuAtError::PayloadContainsEscape);
+            }
+            if out > 0 {
+                self.observe(|o| o.on_payload(&chunk[..out]));
+                self.write_body_chunked(&tail, &chunk[..out], sent, body_len)
+                    .await
+                    .map_err(|e| {
+                        dtu_warn!("dtu_http step=send_payload_from failed: {}", e.as_str());
+                        e
+                    })?;
+                tail.extend_from_slice(&chunk[out.saturating_sub(sequence.len())..out]);
+                let excess = tail.len().saturating_sub(sequence.len());
+                tail.drain(..excess);
+                sent += out;
+            }
+            chunk.copy_within(out..avail, 0);
+            held = avail - out;
+        }
+        Ok(())
+    }
+
+    async fn send_save_and_wait_http_ready(&mut self) -> Result<(), DtuAtError> {
+        dtu_debug!("dtu_http >> AT+S");
+        self.observe(|o| o.on_command("AT+S"));
+        self.write_command("AT+S").await?;
+
+        let deadline = Instant::now() + self.config.http_ready_timeout;
+        let mut merged = Vec::with_capacity(self.response_capacity());
+
+        while Instant::now() < deadline {
+            let poll_first_timeout = short_poll_timeout(self.config.at_first_timeout);
+            let chunk = match self
+                .read_until_idle_quiet(poll_first_timeout, self.config.at_idle_timeout)
+                .await
+            {
+                Ok(c) => c,
+                Err(DtuAtError::Timeout) => {
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if chunk.is_empty() {
+                continue;
+            }
+
+            self.log_response_preview("save_wait", &chunk);
+            self.observe(|o| o.on_response(&chunk));
+
+            if merged.len() + chunk.len() <= self.config.max_response_len {
+                merged.extend_from_slice(&chunk);
+            }
+
+            if contains_at_error(&merged, self.config.error_tokens) {
+                return Err(DtuAtError::AtRejected);
+            }
+            if contains_http_fail(&merged, &self.config.markers) {
+                let code = parse_http_fail_code(&merged, &self.config.markers);
+                dtu_warn!("dtu_http save_wait got FS@HTTP FAIL:{}", code);
+                return Err(DtuAtError::HttpFail(code));
+            }
+            if contains_http_ready(&merged, &self.config.markers) {
+                dtu_debug!("dtu_http got FS@HTTP OK, ready for payload");
+                return Ok(());
+            }
+        }
+
+        dtu_warn!("dtu_http wait FS@HTTP OK timeout");
+        self.log_response_preview("save_wait_final", &merged);
+        Err(DtuAtError::Timeout)
+    }
+
+    /// 查询并打印链路状态；设置了 `min_signal_dbm` 且测得信号低于阈值时返回 `WeakSignal`。
+    ///
+    /// 查询失败或信号未知时只记录日志，不阻止发送。
+    async fn check_link_status(&mut self) -> Result<(), DtuAtError> {
+        let status = match self.read_link_status().await {
+            Ok(status) => status,
+            Err(e) => {
+                dtu_warn!("dtu_http link query CREG failed: {}", e.as_str());
+                return Ok(());
+            }
+        };
+        dtu_debug!(
+            "dtu_http link registration={} rssi_dbm={:?} run_state={:?}",
+            status.registration.as_str(),
+            status.rssi_dbm,
+            status.run_state
+        );
+
+        if let (Some(min), Some(dbm)) = (self.config.min_signal_dbm, status.rssi_dbm)
+            && dbm < min
+        {
+            dtu_warn!("dtu_http signal {}dBm below min_signal_dbm {}dBm", dbm, min);
+            return Err(DtuAtError::WeakSignal(dbm));
+        }
+        Ok(())
+    }
+
+    /// 在命令模式下依次查询 CREG / CSQ / RUNST（见 `query_link_status`）。
+    async fn read_link_status(&mut self) -> Result<LinkStatus, DtuAtError> {
+        let rsp = self.send_query_cmd("AT+CREG?").await?;
+        let registration = parse_creg_state(&rsp).unwrap_or(RegistrationState::Unknown);
+
+        let rssi_dbm = match self.send_query_cmd("AT+CSQ").await {
+            Ok(rsp) => parse_csq_rssi(&rsp).and_then(rssi_to_dbm),
+            Err(e) => {
+                dtu_warn!("dtu_http link query CSQ failed: {}", e.as_str());
+                None
+            }
+        };
+
+        let run_state = match self.send_query_cmd("AT+RUNST?").await {
+            Ok(rsp) => parse_query_value(&rsp, "RUNST")
+                .or_else(|| parse_info_line(&rsp, "AT+RUNST?", self.config.ok_tokens))
+                .map(String::from),
+            Err(e) => {
+                dtu_warn!("dtu_http link query RUNST failed: {}", e.as_str());
+                None
+            }
+        };
+
+        Ok(LinkStatus {
+            registration,
+            rssi_dbm,
+            run_state,
+        })
+    }
+
+    /// 通道号在 1~4 内，且未改动 `new_on_channel` 固定的通道号。
+    fn validate_channel(&self) -> Result<(), DtuAtError> {
+        if !(1..=4).contains(&self.config.channel) {
+            return Err(DtuAtError::InvalidConfig("channel 必须在 1~4"));
+        }
+        if self
+            .fixed_channel
+            .is_some_and(|ch| ch != self.config.channel)
+        {
+            dtu_warn!(
+                "dtu_http channel changed to {} on a client fixed to channel {:?}",
+                self.config.channel,
+                self.fixed_channel
+            );
+            return Err(DtuAtError::InvalidConfig(
+                "channel 已由 new_on_channel 固定，不能修改",
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_request(&self, req: &HttpRequest<'_>) -> Result<(), DtuAtError> {
+        self.validate_channel()?;
+        if req.url.is_empty() {
+            return Err(DtuAtError::InvalidUrl("url 不能为空"));
+        }
+        let url = effective_url(req, &self.config);
+        if url.len() > self.config.max_url_len {
+            return Err(DtuAtError::InvalidUrl("url exceeds max_url_len"));
+        }
+        if url.bytes().any(|b| b == b' ' || b.is_ascii_control()) {
+            return Err(DtuAtError::InvalidUrl("url 不能包含空格或控制字符"));
+        }
+        let Some(parts) = parse_url(&url) else {
+            return Err(DtuAtError::InvalidUrl("url 缺少 scheme 或 host 不合法"));
+        };
+        if !self.config.allow_any_scheme
+            && !parts.scheme.eq_ignore_ascii_case("http")
+            && !parts.scheme.eq_ignore_ascii_case("https")
+        {
+            dtu_warn!("dtu_http unsupported url scheme: {}", parts.scheme);
+            return Err(DtuAtError::InvalidUrl("unsupported scheme"));
+        }
+        if self.config.require_tls && parts.scheme.eq_ignore_ascii_case("http") {
+            dtu_warn!("dtu_http plaintext http rejected (require_tls)");
+            return Err(DtuAtError::TlsRequired);
+        }
+        if self.config.max_response_len == 0 {
+            return Err(DtuAtError::InvalidConfig("max_response_len 不能为 0"));
+        }
+        if payload_escape_risk(&req.payload(), self.config.command_mode_sequence) {
+            dtu_warn!("dtu_http body starts or ends with command-mode sequence");
+            return Err(DtuAtError::PayloadContainsEscape);
+        }
+        Ok(())
+    }
+
+    /// 进入 DTU 命令模式；若有会话未 `close()` 即被丢弃，随后重新下发其已保存的通道参数。
+    async fn enter_command_mode(&mut self) -> Result<(), DtuAtError> {
+        self.switch_to_command_mode().await?;
+        if !self.pending_restore {
+            return Ok(());
+        }
+        if let Some(state) = self.restore_channel.take() {
+            dtu_debug!("dtu_http enter_cmd: re-applying channel state of dropped session");
+            if let Err(e) = self.apply_channel_state(None, &state).await {
+                dtu_warn!("dtu_http step=restore_channel failed: {}", e.as_str());
+                self.restore_channel = Some(state);
+                return Err(e);
+            }
+        }
+        self.pending_restore = false;
+        Ok(())
+    }
+
+    /// 切换到 DTU 命令模式（状态机实现，覆盖所有 DTU 状态）。
+    ///
+    /// 处理场景：
+    /// - DTU 已在命令模式（AT+S 重启后，或会话未 `close()` 即被丢弃）→ Step 1 AT 探测直接成功
+    /// - DTU 在数据/HTTP 透传模式 → Step 2 `+++` 进入命令模式
+    /// - DTU 正在重启（AT+S 触发）→ Step 3 循环等待直到 `enter_cmd_timeout`
+    async fn switch_to_command_mode(&mut self) -> Result<(), DtuAtError> {
+        let deadline = Instant::now() + self.config.enter_cmd_timeout;
+
+        // Step 1: 丢弃 UART 残留数据，然后快速 AT 探测。
+        // 如果 DTU 已在命令模式（如 AT+S 后重启回命令模式），直接返回。
+        self.drain_uart().await;
+        match self.quick_at_probe().await {
+            Ok(()) => {
+                dtu_debug!("dtu_http enter_cmd: already in command mode (AT probe)");
+                return Ok(());
+            }
+            // ERROR: DTU 有响应但在非命令模式（数据模式把 AT\r\n 当 payload 转发）
+            // Timeout / BadResponse: DTU 不在命令模式或处于重启中
+            // 以上均继续走 +++ 流程
+            Err(
+                DtuAtError::AtRejected
+                | DtuAtError::CmeError(_)
+                | DtuAtError::CmsError(_)
+                | DtuAtError::BadResponse
+                | DtuAtError::Timeout,
+            ) => {}
+            Err(e) => return Err(e),
+        }
+
+        // Step 2: guard time + 转义序列（默认 `+++`），从数据/HTTP 透传模式进入命令模式。
+        let sequence = self.config.command_mode_sequence;
+        if sequence.is_empty() {
+            return Err(DtuAtError::InvalidConfig("command_mode_sequence 不能为空"));
+        }
+        let sequence_text = core::str::from_utf8(sequence).unwrap_or("<escape>");
+        dtu_debug!("dtu_http enter command mode via {}", sequence_text);
+        Timer::after(self.config.cmd_guard_time).await;
+        self.observe(|o| o.on_command(sequence_text));
+        self.write_all(sequence).await?;
+        // +++ 后同样需要静默窗口，让 DTU 识别转义序列
+        Timer::after(Duration::from_millis(300)).await;
+
+        match self
+            .read_until_idle_quiet(self.config.at_first_timeout, self.config.at_idle_timeout)
+            .await
+        {
+            Ok(rsp) => {
+                self.log_response_preview("enter_cmd", &rsp);
+                self.observe(|o| o.on_response(&rsp));
+
+                if contains_ok(&rsp, self.config.ok_tokens, &self.config.markers) {
+                    return Ok(());
+                }
+
+                if contains_at_error(&rsp, self.config.error_tokens) {
+                    // +++ 被 DTU 当成 AT 命令处理→ 已在命令模式，drain 并用 AT 确认
+                    dtu_warn!("dtu_http +++ got ERROR (already in cmd mode), drain + confirm");
+                    self.drain_uart().await;
+                    return self.quick_at_probe().await;
+                }
+
+                // 收到 URC 等其他内容，进入等待循环
+                dtu_warn!("dtu_http +++ got unexpected data, entering wait loop");
+                self.wait_for_command_mode(deadline).await
+            }
+            Err(DtuAtError::Timeout) => {
+                // +++ 无响应：DTU 可能正在重启（AT+S 触发），循环等待
+                dtu_warn!("dtu_http +++ timeout, DTU may be rebooting, wait for command mode...");
+                self.wait_for_command_mode(deadline).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 单次 `AT\r\n` 快速探测（不重试）。
+    ///
+    /// - `Ok(())`: DTU 在命令模式且响应正常
+    /// - `Err(AtRejected)`: 收到 ERROR（DTU 在数据模式时出现）
+    /// - `Err(Timeout)`: 无响应
+    /// - `Err(BadResponse)`: 收到数据但没有 OK
+    async fn quick_at_probe(&mut self) -> Result<(), DtuAtError> {
+        dtu_debug!("dtu_http >> AT (probe)");
+        self.observe(|o| o.on_command("AT"));
+        self.write_command("AT").await?;
+
+        match self
+            .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
+            .await
+        {
+            Ok(rsp) => {
+                self.log_response_preview("at_probe", &rsp);
+                self.observe(|o| o.on_response(&rsp));
+                if contains_at_error(&rsp, self.config.error_tokens) {
+                    return Err(DtuAtError::AtRejected);
+                }
+                if contains_ok(&rsp, self.config.ok_tokens, &self.config.markers) {
+                    return Ok(());
+                }
+                Err(DtuAtError::BadResponse)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 在 `deadline` 前循环发 `AT\r\n`，等待 DTU 从重启中恢复并进入命令模式。
+    async fn wait_for_command_mode(&mut self, deadline: Instant) -> Result<(), DtuAtError> {
+        let mut attempt = 0u32;
+
+        loop {
+            if Instant::now() >= deadline {
+                dtu_warn!(
+                    "dtu_http wait_cmd deadline exceeded (attempt={})",
+                    attempt
+                );
+                return Err(DtuAtError::Timeout);
+            }
+
+            Timer::after(self.config.enter_cmd_poll).await;
+            attempt += 1;
+            dtu_debug!("dtu_http >> AT (wait_cmd attempt={})", attempt);
+            self.observe(|o| o.on_command("AT"));
+            self.write_command("AT").await?;
+
+            match self
+                .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
+                .await
+            {
+                Ok(rsp) => {
+                    self.log_response_preview("wait_cmd", &rsp);
+                    self.observe(|o| o.on_response(&rsp));
+
+                    if contains_ok(&rsp, self.config.ok_tokens, &self.config.markers) {
+                        dtu_debug!("dtu_http wait_cmd OK (attempt={})", attempt);
+                        return Ok(());
+                    }
+
+                    if contains_at_error(&rsp, self.config.error_tokens) {
+                        // 在命令模式但有残留 ERROR，drain 后继续重试
+                        dtu_warn!(
+                            "dtu_http wait_cmd ERROR (attempt={}), drain and retry",
+                            attempt
+                        );
+                        self.drain_uart().await;
+                    }
+                    // 收到 URC 或其他内容，继续等待
+                }
+                Err(DtuAtError::Timeout) => {
+                    dtu_debug!(
+                        "dtu_http wait_cmd timeout (attempt={}), DTU still starting...",
+                        attempt
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 丢弃 UART 缓冲区中所有待读数据（使用 50ms 短超时，快速排空）。
+    async fn drain_uart(&mut self) {
+        let mut buf = [0u8; 256];
+        let mut total = 0usize;
+        loop {
+            match with_timeout(
+                Duration::from_millis(50),
+                AsyncRead::read(&mut self.transport, &mut buf),
+            )
+            .await
+            {
+                Ok(Ok(n)) if n > 0 => total += n,
+                _ => break,
+            }
+        }
+        if total > 0 {
+            dtu_debug!("dtu_http drain_uart: discarded {} bytes", total);
+        }
+    }
+
+    async fn send_ok_cmd(&mut self, cmd: &str) -> Result<(), DtuAtError> {
+        self.exchange_ok_cmd(cmd, "at_rsp").await.map(|_| ())
+    }
+
+    async fn send_query_cmd(&mut self, cmd: &str) -> Result<Vec<u8>, DtuAtError> {
+        self.exchange_ok_cmd(cmd, "query_rsp").await
+    }
+
+    /// 发送命令并要求响应包含 `OK`：`+CME ERROR: n` / `+CMS ERROR: n` → `CmeError` / `CmsError`，
+    /// 其余 `ERROR` → `AtRejected`，无 `OK` → `BadResponse`。
+    ///
+    /// 开启 `strip_command_echo` 时先去除开头的命令回显再判断。
+    async fn exchange_ok_cmd(
+        &mut self,
+        cmd: &str,
+        tag: &'static str,
+    ) -> Result<Vec<u8>, DtuAtError> {
+        let result = async {
+            let mut rsp = self.exchange_raw_cmd(cmd, tag).await?;
+            if self.config.strip_command_echo {
+                let stripped = strip_command_echo(&rsp, cmd).len();
+                rsp.drain(..rsp.len() - stripped);
+            }
+            self.check_ok_response(&rsp)?;
+            Ok(rsp)
+        }
+        .await;
+
+        if let Err(e) = &result {
+            self.observe(|o| o.on_error(e));
+        }
+        result
+    }
+
+    /// 按 `exchange_ok_cmd` 的规则判断一段响应：扩展错误码优先，其次 `ERROR`，最后要求 `OK`。
+    fn check_ok_response(&self, rsp: &[u8]) -> Result<(), DtuAtError> {
+        if let Some(e) = parse_extended_error(rsp) {
+            return Err(e);
+        }
+        if contains_at_error(rsp, self.config.error_tokens) {
+            return Err(DtuAtError::AtRejected);
+        }
+        if !contains_ok(rsp, self.config.ok_tokens, &self.config.markers) {
+            return Err(DtuAtError::BadResponse);
+        }
+        Ok(())
+    }
+
+    /// 写入 `cmd` + 行结束符并读取响应（不解析 `OK` / `ERROR`）。
+    async fn exchange_raw_cmd(
+        &mut self,
+        cmd: &str,
+        tag: &'static str,
+    ) -> Result<Vec<u8>, DtuAtError> {
+        let shown = loggable(cmd, self.config.redact_secrets);
+        dtu_debug!("dtu_http >> {}", shown);
+        self.observe(|o| o.on_command(&shown));
+        self.write_command(cmd).await?;
+
+        let rsp = self
+            .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
+            .await;
+        #[cfg(feature = "diagnostics")]
+        self.record_exchange(cmd, rsp.as_deref().unwrap_or_default());
+        let rsp = rsp?;
+
+        self.log_response_preview(tag, &rsp);
+        self.observe(|o| o.on_response(&rsp));
+        Ok(rsp)
+    }
+
+    /// 写入 `cmd` 与配置的 `line_terminator`。
+    async fn write_command(&mut self, cmd: &str) -> Result<(), DtuAtError> {
+        self.write_all(cmd.as_bytes()).await?;
+        self.write_all(self.config.line_terminator).await
+    }
+
+    async fn write_all(&mut self, mut buf: &[u8]) -> Result<(), DtuAtError> {
+        while !buf.is_empty() {
+            let written = AsyncWrite::write(&mut self.transport, buf)
+                .await
+                .map_err(|e| DtuAtError::Transport(e.kind()))?;
+
+            if written == 0 {
+                return Err(DtuAtError::WriteZero);
+            }
+            buf = &buf[written..];
+        }
+
+        AsyncWrite::flush(&mut self.transport)
+            .await
+            .map_err(|e| DtuAtError::Transport(e.kind()))?;
+        Ok(())
+    }
+
+    /// 按 `payload_chunk_size` 分块写入请求体的一段（`sent` 为此前已发送字节数，
+    /// `prev` 为紧接在 `data` 之前发送的字节），块之间等待 `inter_chunk_delay`。
+    ///
+    /// 紧邻 `command_mode_sequence` 的分界不等待，避免停顿构成序列前后的 guard time。
+    async fn write_body_chunked(
+        &mut self,
+        prev: &[u8],
+        data: &[u8],
+        mut sent: usize,
+        total: usize,
+    ) -> Result<(), DtuAtError> {
+        let chunk_size = match self.config.payload_chunk_size {
+            0 => data.len().max(1),
+            n => n,
+        };
+        let sequence = self.config.command_mode_sequence;
+        for (i, piece) in data.chunks(chunk_size).enumerate() {
+            if sent > 0 && self.config.inter_chunk_delay.as_ticks() > 0 {
+                if escape_at_boundary(prev, data, i * chunk_size, sequence) {
+                    dtu_debug!("dtu_http skip inter-chunk delay next to command-mode sequence");
+                } else {
+                    Timer::after(self.config.inter_chunk_delay).await;
+                }
+            }
+            self.write_payload(piece, sent, total).await?;
+            sent += piece.len();
+        }
+        Ok(())
+    }
+
+    /// 与 `write_all` 相同，但每次底层写入后报告上传进度（`sent` 为此前已发送字节数）。
+    async fn write_payload(
+        &mut self,
+        mut buf: &[u8],
+        mut sent: usize,
+        total: usize,
+    ) -> Result<(), DtuAtError> {
+        while !buf.is_empty() {
+            let written = AsyncWrite::write(&mut self.transport, buf)
+                .await
+                .map_err(|e| DtuAtError::Transport(e.kind()))?;
+
+            if written == 0 {
+                return Err(DtuAtError::WriteZero);
+            }
+            buf = &buf[written..];
+            sent += written;
+            self.report_upload(sent, total);
+        }
+
+        AsyncWrite::flush(&mut self.transport)
+            .await
+            .map_err(|e| DtuAtError::Transport(e.kind()))?;
+        Ok(())
+    }
+
+    async fn read_until_idle(
+        &mut self,
+        first_timeout: Duration,
+        idle_timeout: Duration,
+    ) -> Result<Vec<u8>, DtuAtError> {
+        self.read_until_idle_impl(first_timeout, idle_timeout, true)
+            .await
+    }
+
+    async fn read_until_idle_quiet(
+        &mut self,
+        first_timeout: Duration,
+        idle_timeout: Duration,
+    ) -> Result<Vec<u8>, DtuAtError> {
+        self.read_until_idle_impl(first_timeout, idle_timeout, false)
+            .await
+    }
+
+    async fn read_until_idle_impl(
+        &mut self,
+        first_timeout: Duration,
+        idle_timeout: Duration,
+        log_first_timeout: bool,
+    ) -> Result<Vec<u8>, DtuAtError> {
+        // AT 响应通常只有几十字节，从空缓冲按需增长
+        let mut out = Vec::new();
+        let chunk_len = self.config.read_chunk_size.max(MIN_READ_CHUNK_SIZE);
+        self.read_buf.resize(chunk_len, 0);
+        let mut got_any = false;
+
+        loop {
+            let timeout = if got_any { idle_timeout } else { first_timeout };
+            let read_result = with_timeout(
+                timeout,
+                AsyncRead::read(&mut self.transport, &mut self.read_buf),
+            )
+            .await;
+
+            let n = match read_result {
+                Ok(result) => result.map_err(|e| DtuAtError::Transport(e.kind()))?,
+                Err(_) => {
+                    if got_any {
+                        dtu_debug!(
+                            "dtu_http read idle timeout after receiving bytes, stop collecting"
+                        );
+                        break;
+                    }
+                    if log_first_timeout {
+                        dtu_warn!("dtu_http read first byte timeout");
+                    }
+                    return Err(DtuAtError::Timeout);
+                }
+            };
+
+            if n == 0 {
+                break;
+            }
+
+            got_any = true;
+            if out.len() + n > self.config.max_response_len {
+                return Err(DtuAtError::ResponseTooLarge);
+            }
+            out.extend_from_slice(&self.read_buf[..n]);
+        }
+
+        Ok(out)
+    }
+
+    /// 与 `read_until_idle_impl` 相同的空闲判定，但直接读入 `buf[len..]`，返回新的总长度。
+    ///
+    /// 已接收数据以 `terminal_markers` 中任一标记所在的完整行结尾时提前返回；
+    /// 缓冲写满后仍收到数据时返回 `ResponseTooLarge`。
+    async fn read_until_idle_into(
+        &mut self,
+        buf: &mut ResponseBuf<'_>,
+        mut len: usize,
+        first_timeout: Duration,
+        idle_timeout: Duration,
+        log_first_timeout: bool,
+    ) -> Result<usize, DtuAtError> {
+        let mut got_any = false;
+        let mut overflow_probe = [0u8; 1];
+        let grow = self.config.read_chunk_size.max(MIN_READ_CHUNK_SIZE);
+
+        loop {
+            let timeout = if got_any { idle_timeout } else { first_timeout };
+            let spare = buf.spare(len, grow);
+            let dst: &mut [u8] = if spare.is_empty() {
+                &mut overflow_probe
+            } else {
+                let n = spare.len().min(grow);
+                &mut spare[..n]
+            };
+            let read_result =
+                with_timeout(timeout, AsyncRead::read(&mut self.transport, dst)).await;
+
+            let n = match read_result {
+                Ok(result) => result.map_err(|e| DtuAtError::Transport(e.kind()))?,
+                Err(_) => {
+                    if got_any {
+                        dtu_debug!(
+                            "dtu_http read idle timeout after receiving bytes, stop collecting"
+                        );
+                        break;
+                    }
+                    if log_first_timeout {
+                        dtu_warn!("dtu_http read first byte timeout");
+                    }
+                    return Err(DtuAtError::Timeout);
+                }
+            };
+
+            if n == 0 {
+                break;
+            }
+            if len >= buf.limit() {
+                return Err(DtuAtError::ResponseTooLarge);
+            }
+
+            if self.first_byte_at.is_none() {
+                self.first_byte_at = Some(Instant::now());
+            }
+            got_any = true;
+            len += n;
+
+            if ends_with_terminal_line(&buf[..len], self.config.terminal_markers) {
+                dtu_debug!("dtu_http terminal marker seen, stop collecting");
+                break;
+            }
+            // 已收齐声明长度的 body：丢弃其后的残留（如 URC 行），不再等待空闲超时
+            if let Some(end) = self.content_length_end(&buf[..len])
+                && len >= end
+            {
+                dtu_debug!("dtu_http content-length body complete, stop collecting");
+                len = end;
+                break;
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+/// 连续请求会话（由 [`DtuAtHttpClient::session`] 创建）。
+///
+/// 模块在 `AT+S` 后进入 HTTP 透传，之后每写入一次 payload 即按已保存的通道参数发起一次请求。
+/// 会话记录已生效的参数：下一次请求参数完全相同时直接发送 payload，不再 `+++` / `AT+S`；
+/// 有变化时重新进入命令模式，只下发变化的项后再保存。
+///
+/// 会话期间不做请求级重试；请求失败后模块状态未知，下一次请求会完整重新配置。
+///
+/// 会话同时充当命令模式守卫（见 [`DtuAtHttpClient::enter_command_mode_guard`]）：
+/// [`send_command`](Self::send_command) 在需要时重新进入命令模式。异步 `Drop` 尚不可用，
+/// 仍停留在命令模式时应调用 [`close`](Self::close) 回到透传模式；未 `close()` 即被丢弃时
+/// （如中途 `?` 返回），析构只在客户端记录待恢复标记与已保存的通道参数
+/// （[`DtuAtHttpClient::needs_restore`]），下一次进入命令模式时自动恢复，
+/// 也可调用 [`DtuAtHttpClient::restore_transparent_mode`] 主动恢复。
+pub struct CommandSession<'c, T> {
+    client: &'c mut DtuAtHttpClient<T>,
+    /// 最近一次成功保存（`AT+S`）的通道参数。
+    applied: Option<ChannelState>,
+    in_command_mode: bool,
+    /// 上次下发参数中途失败：模块参数不确定，下一次请求重新确认命令模式并完整下发。
+    dirty: bool,
+}
+
+impl<T: AsyncRead + AsyncWrite> CommandSession<'_, T> {
+    /// 在会话上发起请求，复用已生效的通道参数。
+    ///
+    /// 响应用完后应交给 [`DtuAtHttpClient::recycle_response`] 归还缓冲，见 [`DtuAtHttpClient::request`]。
+    pub async fn request_on_session(
+        &mut self,
+        req: &HttpRequest<'_>,
+    ) -> Result<HttpResponse, DtuAtError> {
+        self.client.validate_request(req)?;
+        let next = ChannelState::new(req, &self.client.config)?;
+
+        if self.in_command_mode || self.dirty || self.applied.as_ref() != Some(&next) {
+            if !self.in_command_mode || self.dirty {
+                self.client.enter_command_mode().await?;
+                self.in_command_mode = true;
+            }
+            let prev = if self.dirty {
+                None
+            } else {
+                self.applied.as_ref()
+            };
+            let started = match self.client.apply_channel_state(prev, &next).await {
+                Ok(()) => self.client.start_http_mode().await,
+                Err(e) => Err(e),
+            };
+            // 失败时模块多半仍在命令模式：保留 `in_command_mode` 与已保存的参数，
+            // 丢弃会话时据此恢复
+            if let Err(e) = started {
+                self.dirty = true;
+                return Err(e);
+            }
+            self.in_command_mode = false;
+            self.dirty = false;
+            self.applied = Some(next);
+        } else {
+            dtu_debug!("dtu_http session: channel unchanged, send payload directly");
+        }
+
+        let mut buf = self.client.take_scratch();
+        let max = self.client.config.max_response_len;
+        let result = async {
+            let payload = req.payload();
+            self.client.send_payload(&payload).await?;
+            self.client
+                .read_http_response(
+                    req,
+                    &mut ResponseBuf::Growable(&mut buf, max),
+                    Some(&payload),
+                )
+                .await
+        }
+        .await;
+        let len = match result {
+            Ok(len) => len,
+            Err(e) => {
+                // 失败后模块状态未知，下一次请求完整重新配置
+                self.applied = None;
+                self.client.scratch = buf;
+                return Err(e);
+            }
+        };
+
+        buf.truncate(len);
+        Ok(HttpResponse {
+            status_code: parse_status_code(&buf, &self.client.config.markers),
+            raw: buf,
+            markers: self.client.config.markers,
+        })
+    }
+
+    /// 发送任意 AT 命令，原样返回响应（见 [`DtuAtHttpClient::send_command`]）；
+    /// 模块处于 HTTP 透传时先重新进入命令模式。
+    ///
+    /// 修改通道参数的命令（`AT+HTPURL` 等）不会更新会话记录的参数，应避免在会话中发送。
+    pub async fn send_command(&mut self, cmd: &str) -> Result<Vec<u8>, DtuAtError> {
+        validate_command(cmd)?;
+        self.ensure_command_mode().await?;
+        self.client
+            .exchange_raw_cmd(cmd, "raw_cmd")
+            .await
+            .map_err(|e| {
+                self.client.observe(|o| o.on_error(&e));
+                e
+            })
+    }
+
+    /// 同 [`send_command`](Self::send_command)，但要求响应包含 `OK`。
+    pub async fn send_command_expect_ok(&mut self, cmd: &str) -> Result<(), DtuAtError> {
+        validate_command(cmd)?;
+        self.ensure_command_mode().await?;
+        self.client.send_ok_cmd(cmd).await
+    }
+
+    async fn ensure_command_mode(&mut self) -> Result<(), DtuAtError> {
+        if !self.in_command_mode {
+            self.client.enter_command_mode().await?;
+            self.in_command_mode = true;
+        }
+        Ok(())
+    }
+
+    /// 结束会话：仍处于命令模式时发送 `AT+ENTM` 回到透传模式；失败时保留待恢复标记。
+    pub async fn close(mut self) -> Result<(), DtuAtError> {
+        if self.in_command_mode {
+            self.client.exit_command_mode().await?;
+            self.in_command_mode = false;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for CommandSession<'_, T> {
+    fn drop(&mut self) {
+        if self.in_command_mode {
+            dtu_warn!("dtu_http session dropped in command mode, restore pending");
+            self.client.pending_restore = true;
+            self.client.restore_channel = self.applied.take();
+        }
+    }
+}
+
+/// JSON 序列化缓冲的初始 / 最大长度。
+#[cfg(feature = "serde")]
+const JSON_BUF_INITIAL: usize = 256;
+#[cfg(feature = "serde")]
+const JSON_BUF_MAX: usize = 64 * 1024;
+
+/// 序列化为 JSON 字节；缓冲不足时倍增重试。
+#[cfg(feature = "serde")]
+fn serialize_json<V: serde::Serialize + ?Sized>(value: &V) -> Result<Vec<u8>, DtuAtError> {
+    let mut buf = vec![0u8; JSON_BUF_INITIAL];
+    loop {
+        match serde_json_core::to_slice(value, &mut buf) {
+            Ok(len) => {
+                buf.truncate(len);
+                return Ok(buf);
+            }
+            Err(serde_json_core::ser::Error::BufferFull) if buf.len() < JSON_BUF_MAX => {
+                buf.resize(buf.len() * 2, 0);
+            }
+            Err(_) => return Err(DtuAtError::Serde("json serialize: body too large")),
+        }
+    }
+}
+
+/// HTTP 响应缓冲：`request_into` 的固定切片，或按需增长、不超过上限的 `Vec`。
+///
+/// 解引用为当前已分配（已初始化）的区域；有效数据长度由调用方另行记录。
+enum ResponseBuf<'b> {
+    Fixed(&'b mut [u8]),
+    Growable(&'b mut Vec<u8>, usize),
+}
+
+impl ResponseBuf<'_> {
+    /// 响应长度上限。
+    fn limit(&self) -> usize {
+        match self {
+            Self::Fixed(buf) => buf.len(),
+            Self::Growable(_, max) => *max,
+        }
+    }
+
+    /// `[len..]` 的可写区域；`Growable` 写满时按 `grow` 字节扩容（不超过上限），达到上限时为空。
+    fn spare(&mut self, len: usize, grow: usize) -> &mut [u8] {
+        if let Self::Growable(vec, max) = self
+            && len >= vec.len()
+            && vec.len() < *max
+        {
+            vec.resize((len + grow).min(*max), 0);
+        }
+        let buf: &mut [u8] = self;
+        let start = len.min(buf.len());
+        &mut buf[start..]
+    }
+}
+
+impl core::ops::Deref for ResponseBuf<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Fixed(buf) => buf,
+            Self::Growable(vec, _) => vec,
+        }
+    }
+}
+
+impl core::ops::DerefMut for ResponseBuf<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            Self::Fixed(buf) => buf,
+            Self::Growable(vec, _) => vec,
+        }
+    }
+}
+
+/// 一次请求在模块通道上需要生效的 AT 参数（会话模式下用于比较差异）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChannelState {
+    method: HttpMethod,
+    url: String,
+    head_lines: Vec<String>,
+    response_filter_mask: u8,
+    request_timeout_secs: u16,
+    data_type: HttpDataType,
+}
+
+impl ChannelState {
+    fn new(req: &HttpRequest<'_>, config: &DtuAtHttpConfig) -> Result<Self, DtuAtError> {
+        let head_lines = build_head_lines(req, config).map_err(DtuAtError::InvalidConfig)?;
+        Ok(Self {
+            method: req.method,
+            url: effective_url(req, config),
+            head_lines,
+            response_filter_mask: config.response_filter_mask,
+            request_timeout_secs: config.request_timeout_secs,
+            data_type: req.data_type,
+        })
+    }
+}
+
+/// 实际下发给 `AT+HTPURL` 的 URL：[`HttpRequest::build_url`] 的结果，
+/// 开启 `upgrade_insecure` 时 `http://` 改写为 `https://`。
+///
+/// 改写时显式的 `:80`（http 默认端口）一并去掉，改用 https 默认端口；其他显式端口保持不变。
+fn effective_url(req: &HttpRequest<'_>, config: &DtuAtHttpConfig) -> String {
+    let url = req.build_url();
+    if config.upgrade_insecure
+        && let Some(prefix) = url.get(..7)
+        && prefix.eq_ignore_ascii_case("http://")
+    {
+        dtu_debug!("dtu_http upgrade insecure url to https");
+        let rest = &url[7..];
+        let (authority, tail) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+        let authority = match parse_url(&url) {
+            Some(parts) if parts.port == Some(80) => authority
+                .rsplit_once(':')
+                .map_or(authority, |(host, _)| host),
+            _ => authority,
+        };
+        return format!("https://{}{}", authority, tail);
+    }
+    url
+}
+
+/// 请求体是否以转义序列开头或结尾。
+///
+/// 模块只在序列前后都有 guard time 静默时才切回命令模式。首尾位置紧邻写入前 / 后的静默期，
+/// 无法避免，直接拒绝；中间出现的序列只在写入连续时安全，分块写入因此在紧邻序列的分界
+/// 跳过 `inter_chunk_delay`（见 [`escape_at_boundary`]）。流式上传时读取器供数停顿同样会
+/// 形成静默，需由调用方保证连续供数。
+fn payload_escape_risk(body: &[u8], sequence: &[u8]) -> bool {
+    payload_escape_risk_at(body, true, true, sequence)
+}
+
+/// 按需检查 `data` 的开头 / 结尾是否为转义序列（流式上传只在含请求体首尾时检查）。
+fn payload_escape_risk_at(data: &[u8], start: bool, end: bool, sequence: &[u8]) -> bool {
+    !sequence.is_empty()
+        && ((start && data.starts_with(sequence)) || (end && data.ends_with(sequence)))
+}
+
+/// 请求体在 `prev ++ data[..off]` 与 `data[off..]` 之间的分界是否紧邻转义序列。
+///
+/// 分界前恰以序列结尾、分界后以序列开头，或分界后剩余数据是序列的前缀（序列可能延续到
+/// 下一次读取）时返回 `true`。
+fn escape_at_boundary(prev: &[u8], data: &[u8], off: usize, sequence: &[u8]) -> bool {
+    if sequence.is_empty() {
+        return false;
+    }
+    let (before, after) = data.split_at(off);
+    let k = before.len().min(sequence.len());
+    let (head, rest) = sequence.split_at(sequence.len() - k);
+    let ends = before.ends_with(rest) && prev.ends_with(head);
+    let starts = if after.len() >= sequence.len() {
+        after.starts_with(sequence)
+    } else {
+        sequence.starts_with(after)
+    };
+    ends || starts
+}
+
+fn elapsed_ms(from: Instant, to: Instant) -> u32 {
+    u32::try_from(to.saturating_duration_since(from).as_millis()).unwrap_or(u32::MAX)
+}
+
+/// `set_baud` 接受的波特率。
+const SUPPORTED_BAUD_RATES: [u32; 9] = [
+    9600, 19200, 38400, 57600, 115200, 230400, 460800, 614400, 921600,
+];
+
+/// `read_chunk_size` 的下限，避免过小的分块导致频繁的超时轮询。
+const MIN_READ_CHUNK_SIZE: usize = 32;
+
+/// `recent_exchanges` 每条记录保留的响应字节数上限。
+#[cfg(feature = "diagnostics")]
+const RECENT_RESPONSE_MAX: usize = 64;
+
+fn validate_command(cmd: &str) -> Result<(), DtuAtError> {
+    if cmd.is_empty() {
+        return Err(DtuAtError::InvalidConfig("command 不能为空"));
+    }
+    if cmd.bytes().any(|b| b == b'\r' || b == b'\n') {
+        return Err(DtuAtError::InvalidConfig("command 不能包含 CR / LF"));
+    }
+    Ok(())
+}
+
+/// 第 `retry` 次重试前的退避时长：`retry_backoff_base * 2^(retry-1)`，不超过 `retry_backoff_max`。
+fn retry_backoff(config: &DtuAtHttpConfig, retry: u8) -> Duration {
+    let base = config.retry_backoff_base.as_ticks();
+    let factor = 1u64 << core::cmp::min(retry.saturating_sub(1), 32);
+    let ticks = base.saturating_mul(factor);
+    Duration::from_ticks(core::cmp::min(ticks, config.retry_backoff_max.as_ticks()))
+}
+
+fn short_poll_timeout(base: Duration) -> Duration {
+    if base.as_millis() > 800 {
+        Duration::from_millis(800)
+    } else {
+        base
+    }
+}
+
+fn log_response_preview(tag: &'static str, buf: &[u8], redact: bool) {
+    let preview = response_preview(buf);
+
+    if let Ok(text) = core::str::from_utf8(preview) {
+        dtu_debug!(
+            "dtu_http {} rsp_len={}, preview={}",
+            tag,
+            buf.len(),
+            loggable(text, redact)
+        );
+    } else {
+        let hex = if redact {
+            bytes_to_hex(&redact_authorization_bytes(preview))
+        } else {
+            bytes_to_hex(preview)
+        };
+        dtu_debug!(
+            "dtu_http {} rsp_len={}, preview_hex={}",
+            tag,
+            buf.len(),
+            hex
+        );
+    }
+}
+
+/// 日志输出前按需脱敏 `Authorization` 头（见 `redact_secrets` 配置）。
+fn loggable(text: &str, redact: bool) -> Cow<'_, str> {
+    if redact {
+        redact_authorization(text)
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+fn bytes_to_hex(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (idx, b) in data.iter().enumerate() {
+        if idx > 0 {
+            let _ = out.write_str(" ");
+        }
+        let _ = write!(&mut out, "{:02X}", b);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockTransport, test_config};
+    use embassy_futures::block_on;
+
+    const OK: &[u8] = b"\r\nOK\r\n";
+
+    /// 通道参数（WKMOD 之后到 HTPDT）的脚本轮次，对应 `GET http://example.com/`。
+    fn channel_setup(mock: MockTransport) -> MockTransport {
+        mock.expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+    }
+
+    /// 读写都失败的传输层，错误类型直接用 `ErrorKind`。
+    struct BrokenTransport;
+
+    impl embedded_io_async::ErrorType for BrokenTransport {
+        type Error = embedded_io_async::ErrorKind;
+    }
+
+    impl AsyncRead for BrokenTransport {
+        async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Err(embedded_io_async::ErrorKind::BrokenPipe)
+        }
+    }
+
+    impl AsyncWrite for BrokenTransport {
+        async fn write(&mut self, _buf: &[u8]) -> Result<usize, Self::Error> {
+            Err(embedded_io_async::ErrorKind::BrokenPipe)
+        }
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transport_error_keeps_kind() {
+        let mut client = DtuAtHttpClient::new(BrokenTransport, test_config());
+        let err = block_on(client.send_command("AT")).unwrap_err();
+        assert_eq!(
+            err.transport_kind(),
+            Some(embedded_io_async::ErrorKind::BrokenPipe)
+        );
+        assert!(core::error::Error::source(&err).is_some());
+    }
+
+    /// 完整的 `GET http://example.com/` 脚本，模块随后输出 `response`。
+    fn get_script(response: &[u8]) -> MockTransport {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", response)
+    }
+
+    fn get_request() -> HttpRequest<'static> {
+        HttpRequest::new(HttpMethod::Get, "http://example.com/")
+    }
+
+    #[test]
+    fn response_buffer_grows_on_demand() {
+        let mock = get_script(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let resp = block_on(client.request(&get_request())).unwrap();
+        assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+        assert_eq!(resp.markers, client.config().markers);
+        assert!(resp.raw.capacity() < client.config().max_response_len);
+    }
+
+    #[test]
+    fn response_over_limit_is_rejected() {
+        let body = [b'x'; 600];
+        let mut response = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+        response.extend_from_slice(&body);
+        let mock = get_script(&response);
+        let config = DtuAtHttpConfig {
+            max_response_len: 512,
+            max_request_attempts: 1,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let err = block_on(client.request(&get_request())).unwrap_err();
+        assert_eq!(err, DtuAtError::ResponseTooLarge);
+    }
+
+    #[test]
+    fn large_response_is_identical_for_any_chunk_size() {
+        let body: Vec<u8> = (0..1500u32).map(|i| b'a' + (i % 26) as u8).collect();
+        let mut response =
+            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        response.extend_from_slice(&body);
+
+        let raws: Vec<Vec<u8>> = [32, 100, 256, 4096]
+            .into_iter()
+            .map(|read_chunk_size| {
+                let config = DtuAtHttpConfig {
+                    read_chunk_size,
+                    ..test_config()
+                };
+                let mut client = DtuAtHttpClient::new(get_script(&response), config);
+                let resp = block_on(client.request(&get_request())).unwrap();
+                assert_eq!(resp.http_body(), Some(&body[..]));
+                resp.raw
+            })
+            .collect();
+        assert!(raws.iter().all(|raw| *raw == raws[0]));
+    }
+
+    #[test]
+    fn at_response_is_identical_for_any_chunk_size() {
+        let mut reply = b"\r\n".to_vec();
+        reply.extend((0..300u32).map(|i| b'0' + (i % 10) as u8));
+        reply.extend_from_slice(b"\r\nOK\r\n");
+
+        for read_chunk_size in [1, 32, 64, 1024] {
+            let mock = MockTransport::new()
+                .expect(b"AT\r\n", OK)
+                .expect(b"AT+VER\r\n", &reply);
+            let config = DtuAtHttpConfig {
+                read_chunk_size,
+                ..test_config()
+            };
+            let mut client = DtuAtHttpClient::new(mock, config);
+            assert_eq!(block_on(client.send_command("AT+VER")).unwrap(), reply);
+        }
+    }
+
+    #[test]
+    fn transient_error_is_retried_then_succeeds() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", b"\r\nERROR\r\n")
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi");
+
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let resp = block_on(client.request(&get_request())).unwrap();
+        assert_eq!(resp.status_code, Some(200));
+        assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+        client.into_transport().assert_done();
+    }
+
+    /// 收集写入字节的 sink。
+    #[derive(Default)]
+    struct VecSink(Vec<u8>);
+
+    impl embedded_io_async::ErrorType for VecSink {
+        type Error = core::convert::Infallible;
+    }
+
+    impl AsyncWrite for VecSink {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    fn stream(response: &[u8], read_chunk_size: usize) -> (StreamedResponse, Vec<u8>) {
+        let config = DtuAtHttpConfig {
+            read_chunk_size,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(get_script(response), config);
+        let mut sink = VecSink::default();
+        let streamed = block_on(client.request_to_writer(&get_request(), &mut sink)).unwrap();
+        (streamed, sink.0)
+    }
+
+    #[test]
+    fn stream_writes_exactly_content_length_bytes() {
+        let response =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello\r\nFS@HTTP SUCCESS CODE:200\r\n";
+        for read_chunk_size in [32, 4096] {
+            let (streamed, written) = stream(response, read_chunk_size);
+            assert_eq!(written, b"hello");
+            assert_eq!(streamed.status_code, Some(200));
+            assert_eq!(streamed.total_len, 5);
+        }
+    }
+
+    #[test]
+    fn stream_drops_trailing_urc_without_content_length() {
+        let body: Vec<u8> = (0..100u32).map(|i| b'a' + (i % 26) as u8).collect();
+        let mut response = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_vec();
+        response.extend_from_slice(&body);
+        response.extend_from_slice(b"\r\nFS@HTTP SUCCESS CODE:200\r\n");
+        // 32 字节读取使 URC 前的换行与前缀跨两次读取
+        for read_chunk_size in [32, 4096] {
+            let (streamed, written) = stream(&response, read_chunk_size);
+            assert_eq!(written, body);
+            assert_eq!(streamed.total_len, body.len());
+        }
+    }
+
+    #[test]
+    fn stream_body_larger_than_max_response_len() {
+        let body: Vec<u8> = (0..3000u32).map(|i| b'0' + (i % 10) as u8).collect();
+        let mut response =
+            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        response.extend_from_slice(&body);
+        let config = DtuAtHttpConfig {
+            max_response_len: 512,
+            read_chunk_size: 128,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(get_script(&response), config);
+        let mut sink = VecSink::default();
+        let streamed = block_on(client.request_to_writer(&get_request(), &mut sink)).unwrap();
+        assert_eq!(sink.0, body);
+        assert!(streamed.is_success());
+    }
+
+    fn split_body_script(gap: Duration) -> MockTransport {
+        get_script(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello").then(gap, b"world")
+    }
+
+    #[test]
+    fn full_body_is_collected_across_reads() {
+        let config = DtuAtHttpConfig {
+            wait_for_full_body: true,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(split_body_script(Duration::from_millis(60)), config);
+        let resp = block_on(client.request(&get_request())).unwrap();
+        assert_eq!(resp.http_body(), Some(&b"helloworld"[..]));
+    }
+
+    #[test]
+    fn late_body_reports_truncation() {
+        let config = DtuAtHttpConfig {
+            wait_for_full_body: true,
+            max_request_attempts: 1,
+            ..test_config()
+        };
+        let mut client =
+            DtuAtHttpClient::new(split_body_script(Duration::from_millis(400)), config);
+        let err = block_on(client.request(&get_request())).unwrap_err();
+        assert_eq!(err, DtuAtError::BodyTruncated { have: 5, want: 10 });
+    }
+
+    /// 空闲超时设得很长：只有按长度或终止标记提前结束才能很快返回。
+    fn slow_idle_config() -> DtuAtHttpConfig {
+        DtuAtHttpConfig {
+            http_idle_timeout: Duration::from_secs(5),
+            ..test_config()
+        }
+    }
+
+    fn timed_request(mock: MockTransport) -> (HttpResponse, Duration) {
+        let mut client = DtuAtHttpClient::new(mock, slow_idle_config());
+        let started = Instant::now();
+        let resp = block_on(client.request(&get_request())).unwrap();
+        (resp, Instant::now() - started)
+    }
+
+    #[test]
+    fn content_length_stops_at_exact_length() {
+        let (resp, elapsed) = timed_request(get_script(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello\r\nFS@HTTP STRAY\r\n",
+        ));
+        assert!(resp.raw.ends_with(b"\r\n\r\nhello"));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn content_length_split_across_reads() {
+        let mock = get_script(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhel")
+            .then(Duration::from_millis(30), b"lowor")
+            .then(Duration::from_millis(30), b"ld");
+        let (resp, elapsed) = timed_request(mock);
+        assert_eq!(resp.http_body(), Some(&b"helloworld"[..]));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn invalid_content_length_falls_back_to_terminal_marker() {
+        let (resp, elapsed) = timed_request(get_script(
+            b"HTTP/1.1 200 OK\r\nContent-Length: abc\r\n\r\nhello\r\nFS@HTTP SUCCESS CODE:200\r\n",
+        ));
+        assert!(resp.raw.ends_with(b"FS@HTTP SUCCESS CODE:200\r\n"));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn terminal_marker_stops_without_idle_wait() {
+        let mock = get_script(b"HTTP/1.1 200 OK\r\n\r\nhello").then(
+            Duration::from_millis(30),
+            b"\r\nFS@HTTP SUCCESS CODE:200\r\n",
+        );
+        let (resp, elapsed) = timed_request(mock);
+        assert_eq!(resp.status_code, Some(200));
+        assert!(resp.raw.ends_with(b"FS@HTTP SUCCESS CODE:200\r\n"));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn session_enters_command_mode_once() {
+        // 模块处于透传模式：AT 探测无响应，需要 +++
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            let mut session = client.session().await.unwrap();
+            let first = session.request_on_session(&get_request()).await.unwrap();
+            let second = session.request_on_session(&get_request()).await.unwrap();
+            assert_eq!(first.http_body(), Some(&b"one"[..]));
+            assert_eq!(second.http_body(), Some(&b"two"[..]));
+            session.close().await.unwrap();
+        });
+
+        let transport = client.into_transport();
+        transport.assert_done();
+        let escapes = transport
+            .written()
+            .windows(3)
+            .filter(|w| *w == b"+++")
+            .count();
+        assert_eq!(escapes, 1);
+    }
+
+    /// 按顺序记录观察者事件：`> 命令` 与 `< 响应`（响应按 UTF-8 有损转换）。
+    #[derive(Clone, Default)]
+    struct Recorder(std::rc::Rc<core::cell::RefCell<Vec<String>>>);
+
+    impl Recorder {
+        fn events(&self) -> Vec<String> {
+            self.0.borrow().clone()
+        }
+    }
+
+    impl AtObserver for Recorder {
+        fn on_command(&mut self, cmd: &str) {
+            self.0.borrow_mut().push(format!("> {}", cmd));
+        }
+        fn on_response(&mut self, rsp: &[u8]) {
+            self.0
+                .borrow_mut()
+                .push(format!("< {}", String::from_utf8_lossy(rsp)));
+        }
+    }
+
+    const PEM: &[u8] = b"-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+
+    fn cert_script(reply: MockTransport) -> DtuAtHttpClient<MockTransport> {
+        let mock = reply.expect(PEM, OK);
+        DtuAtHttpClient::new(mock, test_config())
+    }
+
+    #[test]
+    fn certificate_is_written_after_prompt() {
+        let cmd = format!("AT+SSLCERT=1,0,{}\r\n", PEM.len());
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(cmd.as_bytes(), b"")
+            .then(Duration::from_millis(20), b"\r\n> ");
+        let mut client = cert_script(mock);
+        let recorder = Recorder::default();
+        client.set_observer(recorder.clone());
+        block_on(client.upload_certificate(1, CertKind::Ca, PEM)).unwrap();
+
+        // 提示符在命令阶段读到，数据之后只收到 OK
+        let events = recorder.events();
+        let cmd_idx = events
+            .iter()
+            .position(|e| e.starts_with("> AT+SSLCERT"))
+            .unwrap();
+        assert_eq!(events[cmd_idx + 1], "< \r\n> ");
+        assert_eq!(events[cmd_idx + 2..], ["< \r\nOK\r\n"]);
+        let transport = client.into_transport();
+        transport.assert_done();
+        assert!(
+            transport
+                .written()
+                .ends_with(&[cmd.as_bytes(), PEM].concat())
+        );
+    }
+
+    #[test]
+    fn certificate_is_written_when_prompt_times_out() {
+        let cmd = format!("AT+SSLCERT=2,2,{}\r\n", PEM.len());
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(cmd.as_bytes(), b"");
+        let mut client = cert_script(mock);
+        block_on(client.upload_certificate(2, CertKind::ClientKey, PEM)).unwrap();
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn certificate_command_error_skips_data() {
+        let cmd = format!("AT+SSLCERT=1,0,{}\r\n", PEM.len());
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(cmd.as_bytes(), b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let err = block_on(client.upload_certificate(1, CertKind::Ca, PEM)).unwrap_err();
+        assert_eq!(err, DtuAtError::AtRejected);
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn enter_sleep_sends_csclk() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSCLK=1\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(client.enter_sleep()).unwrap();
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn enter_sleep_rejected() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSCLK=1\r\n", b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        assert_eq!(block_on(client.enter_sleep()), Err(DtuAtError::AtRejected));
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn wake_probes_until_ok() {
+        let mock = MockTransport::new()
+            // 首个 AT 被睡眠中的模块当作唤醒信号丢弃
+            .expect(b"AT\r\n", b"")
+            .expect(b"AT\r\n", OK)
+            // 唤醒后已在命令模式：后续命令的 AT 探测直接成功，不再发送 +++
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 20,99\r\n\r\nOK\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            client.wake().await.unwrap();
+            assert_eq!(client.query_signal_strength().await.unwrap(), -73);
+        });
+
+        let transport = client.into_transport();
+        transport.assert_done();
+        assert!(find_subslice(transport.written(), b"+++").is_none());
+        assert!(find_subslice(transport.written(), b"CSCLK").is_none());
+    }
+
+    #[test]
+    fn wake_times_out_without_ok() {
+        // 模块一直不回复：探测到 enter_cmd_timeout 为止
+        let mock = (0..64).fold(MockTransport::new(), |m, _| m.expect(b"AT\r\n", b""));
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        assert_eq!(block_on(client.wake()), Err(DtuAtError::Timeout));
+    }
+
+    #[test]
+    fn disable_sleep_sends_csclk_zero() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSCLK=0\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(client.disable_sleep()).unwrap();
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn upgrade_insecure_replaces_default_port() {
+        let config = DtuAtHttpConfig {
+            upgrade_insecure: true,
+            ..test_config()
+        };
+        let upgrade = |url| effective_url(&HttpRequest::new(HttpMethod::Get, url), &config);
+        assert_eq!(upgrade("http://example.com/x"), "https://example.com/x");
+        assert_eq!(upgrade("http://example.com:80/x"), "https://example.com/x");
+        assert_eq!(
+            upgrade("HTTP://u:p@example.com:80"),
+            "https://u:p@example.com"
+        );
+        assert_eq!(upgrade("http://[::1]:80/x?a=1"), "https://[::1]/x?a=1");
+        assert_eq!(
+            upgrade("http://example.com:8080/x"),
+            "https://example.com:8080/x"
+        );
+        assert_eq!(
+            upgrade("https://example.com:80/x"),
+            "https://example.com:80/x"
+        );
+    }
+
+    #[test]
+    fn observer_sees_probe_and_redacted_command() {
+        let cmd = "AT+HTPHD1=Authorization: Bearer s3cr3t[0D][0A]";
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(format!("{}\r\n", cmd).as_bytes(), OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let recorder = Recorder::default();
+        client.set_observer(recorder.clone());
+        block_on(client.send_command(cmd)).unwrap();
+
+        assert_eq!(
+            recorder.events(),
+            [
+                "> AT",
+                "< \r\nOK\r\n",
+                "> AT+HTPHD1=Authorization: Bearer ***[0D][0A]",
+                "< \r\nOK\r\n",
+            ]
+        );
+        // 脱敏只影响观察者，模块仍收到真实凭据
+        assert!(find_subslice(client.into_transport().written(), b"s3cr3t").is_some());
+    }
+
+    #[test]
+    fn observer_sees_wait_for_command_mode_replies() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", b"")
+            .expect(b"AT\r\n", b"\r\nFS@SYS READY\r\n")
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 20,99\r\n\r\nOK\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let recorder = Recorder::default();
+        client.set_observer(recorder.clone());
+        block_on(client.query_signal_strength()).unwrap();
+
+        assert_eq!(
+            recorder.events()[..6],
+            [
+                "> AT",
+                "> +++",
+                "> AT",
+                "< \r\nFS@SYS READY\r\n",
+                "> AT",
+                "< \r\nOK\r\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn capacity_hint_applies_to_http_responses_only() {
+        let mock = get_script(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi")
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+GMR\r\n", b"\r\nV1.0.0\r\nOK\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let hint = client.config().response_capacity_hint;
+
+        let resp = block_on(client.request(&get_request())).unwrap();
+        assert!(resp.raw.capacity() >= hint);
+        let rsp = block_on(client.send_command("AT+GMR")).unwrap();
+        assert!(rsp.capacity() < hint);
+    }
+
+    #[test]
+    fn escape_at_boundary_detects_sequence_on_chunk_edges() {
+        let seq = b"+++";
+        // 分界前以序列结尾 / 分界后以序列开头
+        assert!(escape_at_boundary(&[], b"ab+++cd", 5, seq));
+        assert!(escape_at_boundary(&[], b"ab+++cd", 2, seq));
+        // 序列被分界切开：停顿打断序列本身，不构成转义
+        assert!(!escape_at_boundary(&[], b"ab+++cd", 3, seq));
+        assert!(!escape_at_boundary(&[], b"abcdef", 3, seq));
+        // 分界前的序列跨越上一次读取
+        assert!(escape_at_boundary(b"x+", b"++ab", 2, seq));
+        assert!(!escape_at_boundary(b"x-", b"++ab", 2, seq));
+        // 分界后剩余数据是序列前缀，可能延续到下一次读取
+        assert!(escape_at_boundary(&[], b"ab++", 2, seq));
+        assert!(!escape_at_boundary(&[], b"abcd", 2, seq));
+        assert!(!escape_at_boundary(&[], b"ab+++cd", 5, b""));
+    }
+
+    #[test]
+    fn chunk_delay_is_skipped_next_to_escape_sequence() {
+        let config = DtuAtHttpConfig::builder()
+            .payload_chunk_size(2)
+            .inter_chunk_delay(Duration::from_millis(300))
+            .build()
+            .unwrap();
+        // 分界 2 紧接 "+++" 之前，不等待；分界 4 切开序列、分界 6 远离序列，各等待一次
+        let mock = MockTransport::new().expect(b"ab+++cde", b"");
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let start = Instant::now();
+        block_on(client.write_body_chunked(&[], b"ab+++cde", 0, 8)).unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(600), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(900), "{:?}", elapsed);
+        client.transport.assert_done();
+    }
+
+    #[test]
+    fn streamed_escape_sequence_is_detected_across_reads() {
+        let send = |reads: &[&[u8]], expected: &[u8]| {
+            let mut body = MockTransport::new();
+            let mut len = 0;
+            for read in reads {
+                body.push_read(read);
+                len += read.len();
+            }
+            let mock = MockTransport::new().expect(expected, b"");
+            let mut client = DtuAtHttpClient::new(mock, test_config());
+            let result = block_on(client.send_payload_from(&mut body, len));
+            assert_eq!(client.transport.written(), expected);
+            result
+        };
+
+        // 开头的 "+++" 被拆成 "+" | "++"
+        assert_eq!(
+            send(&[b"+", b"++ab"], b""),
+            Err(DtuAtError::PayloadContainsEscape)
+        );
+        // 结尾的 "+++" 被拆成 "+" | "++"
+        assert_eq!(
+            send(&[b"ab+", b"++"], b"a"),
+            Err(DtuAtError::PayloadContainsEscape)
+        );
+        // 中间跨读取的序列照常发送
+        assert_eq!(send(&[b"a+", b"++b"], b"a+++b"), Ok(()));
+    }
+
+    #[test]
+    fn dropped_session_is_restored_with_channel_state() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none")
+            // 会话上的命令：模块在 HTTP 透传，重新 +++
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 20,0\r\n\r\nOK\r\n")
+            // 恢复：模块仍在命令模式，重新下发会话的通道参数后保存
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock).expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            let mut guard = client.enter_command_mode_guard().await.unwrap();
+            guard.request_on_session(&get_request()).await.unwrap();
+            guard.send_command("AT+CSQ").await.unwrap();
+        });
+        assert!(client.needs_restore());
+
+        block_on(client.restore_transparent_mode()).unwrap();
+        assert!(!client.needs_restore());
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn session_recovers_after_channel_setup_failure() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none")
+            // URL 变化：重新 +++，只下发 HTPURL，被拒绝
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+HTPURL1=http://example.com/b\r\n", b"\r\nERROR\r\n")
+            // 下一次请求：确认命令模式后完整下发
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/b\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let other = HttpRequest::new(HttpMethod::Get, "http://example.com/b");
+
+        block_on(async {
+            let mut session = client.session().await.unwrap();
+            session.request_on_session(&get_request()).await.unwrap();
+            assert_eq!(
+                session.request_on_session(&other).await.unwrap_err(),
+                DtuAtError::AtRejected
+            );
+            let resp = session.request_on_session(&other).await.unwrap();
+            assert_eq!(resp.http_body(), Some(&b"two"[..]));
+            session.close().await.unwrap();
+        });
+        assert!(!client.needs_restore());
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn session_dropped_after_setup_failure_needs_restore() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", b"\r\nERROR\r\n")
+            // 恢复：会话尚未保存过参数，确认命令模式后 AT+ENTM
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+ENTM\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            let mut session = client.session().await.unwrap();
+            assert!(session.request_on_session(&get_request()).await.is_err());
+        });
+        assert!(client.needs_restore());
+        block_on(client.restore_transparent_mode()).unwrap();
+        assert!(!client.needs_restore());
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn send_command_returns_raw_response() {
+        let rsp = b"\r\n+CGPADDR: 1,\"10.0.0.2\"\r\n\r\nOK\r\n";
+        // 已在命令模式：AT 探测成功，不发送 +++
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CGPADDR=1\r\n", rsp)
+            // 不在命令模式：探测无响应，经 guard time 后 +++
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+CGPADDR=1\r\n", b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            assert_eq!(client.send_command("AT+CGPADDR=1").await.unwrap(), rsp);
+            // 不解析 ERROR，原样返回
+            assert_eq!(
+                client.send_command("AT+CGPADDR=1").await.unwrap(),
+                b"\r\nERROR\r\n"
+            );
+        });
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn send_command_expect_ok_maps_error() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CGATT=1\r\n", OK)
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CGATT=1\r\n", b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            client.send_command_expect_ok("AT+CGATT=1").await.unwrap();
+            assert_eq!(
+                client.send_command_expect_ok("AT+CGATT=1").await,
+                Err(DtuAtError::AtRejected)
+            );
+            // 含换行的命令不发送
+            assert!(matches!(
+                client.send_command("AT\r\nAT+Z").await,
+                Err(DtuAtError::InvalidConfig(_))
+            ));
+        });
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn fixed_channel_cannot_be_changed() {
+        let mut client = DtuAtHttpClient::new_on_channel::<2>(MockTransport::new(), test_config());
+        client.config_mut().channel = 3;
+
+        block_on(async {
+            assert!(matches!(
+                client.request(&get_request()).await,
+                Err(DtuAtError::InvalidConfig(_))
+            ));
+            assert!(matches!(
+                client.set_tls_verify(TlsVerify::None).await,
+                Err(DtuAtError::InvalidConfig(_))
+            ));
+        });
+        assert!(client.transport.written().is_empty());
+
+        // 改回固定通道后照常使用
+        client.config_mut().channel = 2;
+        assert!(client.validate_request(&get_request()).is_ok());
+    }
+
+    #[test]
+    fn rejected_extension_method_is_unsupported() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=PUT\r\n", b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let req = HttpRequest::new(HttpMethod::Put, "http://example.com/");
 
-                    if contains_at_error(&rsp) {
-                        // 在命令模式但有残留 ERROR，drain 后继续重试
-                        dtu_warn!(
-                            "dtu_http wait_cmd ERROR (attempt={}), drain and retry",
-                            attempt
-                        );
-                        self.drain_uart().await;
-                    }
-                    // 收到 URC 或其他内容，继续等待
-                }
-                Err(DtuAtError::Timeout) => {
-                    dtu_debug!(
-                        "dtu_http wait_cmd timeout (attempt={}), DTU still starting...",
-                        attempt
-                    );
-                }
-                Err(e) => return Err(e),
-            }
-        }
+        assert_eq!(
+            block_on(client.request(&req)).unwrap_err(),
+            DtuAtError::UnsupportedMethod
+        );
+        assert_eq!(client.last_step(), Some("HTPTP"));
+        client.into_transport().assert_done();
     }
 
-    /// 丢弃 UART 缓冲区中所有待读数据（使用 50ms 短超时，快速排空）。
-    async fn drain_uart(&mut self) {
-        let mut buf = [0u8; 256];
-        let mut total = 0usize;
-        loop {
-            match with_timeout(
-                Duration::from_millis(50),
-                AsyncRead::read(&mut self.transport, &mut buf),
+    /// 响应在 `http_first_timeout`（测试配置 200ms）之后才到达的 GET 脚本。
+    fn late_response_script() -> MockTransport {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"")
+            .then(
+                Duration::from_millis(400),
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi",
             )
-            .await
-            {
-                Ok(Ok(n)) if n > 0 => total += n,
-                _ => break,
-            }
-        }
-        if total > 0 {
-            dtu_debug!("dtu_http drain_uart: discarded {} bytes", total);
-        }
     }
 
-    async fn send_ok_cmd(&mut self, cmd: &str) -> Result<(), DtuAtError> {
-        dtu_debug!("dtu_http >> {}", cmd);
-        self.write_all(cmd.as_bytes()).await?;
-        self.write_all(b"\r\n").await?;
+    #[test]
+    fn per_request_first_timeout_reaches_read() {
+        let config = DtuAtHttpConfig {
+            max_request_attempts: 1,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(late_response_script(), config);
+        let err = block_on(client.request(&get_request())).unwrap_err();
+        assert_eq!(err, DtuAtError::Timeout);
 
-        let rsp = self
-            .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
-            .await?;
+        let mut client = DtuAtHttpClient::new(late_response_script(), test_config());
+        let req = get_request().with_http_first_timeout(Duration::from_secs(1));
+        let resp = block_on(client.request(&req)).unwrap();
+        assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+        client.into_transport().assert_done();
+    }
 
-        log_response_preview("at_rsp", &rsp);
+    #[test]
+    fn exit_command_mode_sends_entm() {
+        let mock = MockTransport::new().expect(b"AT+ENTM\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(client.exit_command_mode()).unwrap();
+        let mock = client.into_transport();
+        mock.assert_done();
+        assert_eq!(mock.written(), b"AT+ENTM\r\n");
 
-        if contains_at_error(&rsp) {
-            return Err(DtuAtError::AtRejected);
-        }
-        if !contains_ok(&rsp) {
-            return Err(DtuAtError::BadResponse);
-        }
-        Ok(())
+        let mock = MockTransport::new().expect(b"AT+ENTM\r\n", b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        assert_eq!(
+            block_on(client.exit_command_mode()),
+            Err(DtuAtError::AtRejected)
+        );
     }
 
-    async fn send_query_cmd(&mut self, cmd: &str) -> Result<Vec<u8>, DtuAtError> {
-        dtu_debug!("dtu_http >> {}", cmd);
-        self.write_all(cmd.as_bytes()).await?;
-        self.write_all(b"\r\n").await?;
+    #[test]
+    fn signal_strength_maps_rssi_to_dbm() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 20,0\r\n\r\nOK\r\n")
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 99,99\r\n\r\nOK\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            assert_eq!(client.query_signal_strength().await, Ok(-73));
+            assert_eq!(
+                client.query_signal_strength().await,
+                Err(DtuAtError::BadResponse)
+            );
+        });
+        client.into_transport().assert_done();
+    }
 
-        let rsp = self
-            .read_until_idle(self.config.at_first_timeout, self.config.at_idle_timeout)
-            .await?;
+    #[test]
+    fn imei_and_iccid_ignore_command_echo() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(
+                b"AT+GSN\r\n",
+                b"AT+GSN\r\n\r\n861234567890123\r\n\r\nOK\r\n",
+            )
+            .expect(b"AT\r\n", OK)
+            .expect(
+                b"AT+ICCID\r\n",
+                b"AT+ICCID\r\n\r\n+ICCID: 89860012345678901234\r\n\r\nOK\r\n",
+            );
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            assert_eq!(client.query_imei().await.unwrap(), "861234567890123");
+            assert_eq!(client.query_iccid().await.unwrap(), "89860012345678901234");
+        });
+        client.into_transport().assert_done();
+    }
 
-        log_response_preview("query_rsp", &rsp);
+    #[test]
+    fn post_form_encodes_body_and_content_type() {
+        let body = b"name=a%20b&tag=x%26y&tag=z";
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=POST\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/form\r\n", OK)
+            .expect(
+                b"AT+HTPHD1=Content-Type: application/x-www-form-urlencoded[0D][0A]\r\n",
+                OK,
+            )
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(body, b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let fields = [("name", "a b"), ("tag", "x&y"), ("tag", "z")];
+        let resp = block_on(client.post_form("http://example.com/form", &fields)).unwrap();
+        assert_eq!(resp.http_body(), Some(&b"ok"[..]));
+        client.into_transport().assert_done();
+    }
 
-        if contains_at_error(&rsp) {
-            return Err(DtuAtError::AtRejected);
-        }
-        if !contains_ok(&rsp) {
-            return Err(DtuAtError::BadResponse);
-        }
+    #[test]
+    fn reboot_waits_out_silence_and_boot_banner() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            // 复位前未回复 OK
+            .expect(b"AT+Z\r\n", b"")
+            // 启动中：探测无响应，之后输出启动信息
+            .expect(b"AT\r\n", b"")
+            .then(Duration::from_millis(100), b"\r\nUSR-DTU BOOT V1.0.3\r\n")
+            .expect(b"AT\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(client.reboot()).unwrap();
+        client.into_transport().assert_done();
+    }
 
-        Ok(rsp)
+    #[test]
+    fn factory_reset_without_confirm_writes_nothing() {
+        let mut client = DtuAtHttpClient::new(MockTransport::new(), test_config());
+        assert!(matches!(
+            block_on(client.factory_reset(false, true)),
+            Err(DtuAtError::InvalidConfig(_))
+        ));
+        assert!(client.into_transport().written().is_empty());
     }
 
-    async fn write_all(&mut self, mut buf: &[u8]) -> Result<(), DtuAtError> {
-        while !buf.is_empty() {
-            let written = AsyncWrite::write(&mut self.transport, buf)
+    #[test]
+    fn configure_apn_writes_cgdcont_and_cgauth() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CGDCONT=1,\"IP\",\"cmnet\"\r\n", OK)
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CGDCONT=1,\"IP\",\"iot.example\"\r\n", OK)
+            .expect(b"AT+CGAUTH=1,1,\"user\",\"\"\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            client.configure_apn("cmnet", None, None).await.unwrap();
+            client
+                .configure_apn("iot.example", Some("user"), None)
                 .await
-                .map_err(DtuAtError::Transport)?;
+                .unwrap();
+            assert!(matches!(
+                client.configure_apn("a\"b", None, None).await,
+                Err(DtuAtError::InvalidConfig(_))
+            ));
+        });
+        client.into_transport().assert_done();
+    }
 
-            if written == 0 {
-                return Err(DtuAtError::WriteZero);
-            }
-            buf = &buf[written..];
+    #[test]
+    fn firmware_version_takes_first_info_line() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(
+                b"AT+VER\r\n",
+                b"AT+VER\r\n\r\n+VER:V1.0.05.000000.0000\r\nBuild Jan 10 2024\r\n\r\nOK\r\n",
+            )
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+VER\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            assert_eq!(
+                client.query_firmware_version().await.unwrap(),
+                "+VER:V1.0.05.000000.0000"
+            );
+            assert_eq!(
+                client.query_firmware_version().await,
+                Err(DtuAtError::BadResponse)
+            );
+        });
+        client.into_transport().assert_done();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_value_round_trips_through_request_and_response() {
+        let value = (42u32, true, -7i16);
+        let body = serialize_json(&value).unwrap();
+        assert_eq!(body, b"[42,true,-7]");
+
+        let mut echo = b"HTTP/1.1 200 OK\r\nContent-Length: 12\r\n\r\n".to_vec();
+        echo.extend_from_slice(&body);
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=POST\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/echo\r\n", OK)
+            .expect(b"AT+HTPHD1=Content-Type: application/json[0D][0A]\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(&body, &echo);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let resp = block_on(client.post_json_value("http://example.com/echo", &value)).unwrap();
+        assert_eq!(resp.json::<(u32, bool, i16)>(), Ok(value));
+        client.into_transport().assert_done();
+    }
+
+    fn redirect_config(max_redirects: u8) -> DtuAtHttpConfig {
+        DtuAtHttpConfig {
+            follow_redirects: true,
+            max_redirects,
+            ..test_config()
         }
+    }
 
-        AsyncWrite::flush(&mut self.transport)
-            .await
-            .map_err(DtuAtError::Transport)?;
-        Ok(())
+    /// 第二跳（`/next`）的脚本轮次：参数里只有 URL 变化。
+    fn redirect_hop(mock: MockTransport, response: &[u8]) -> MockTransport {
+        mock.expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/next\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", response)
     }
 
-    async fn read_until_idle(
-        &mut self,
-        first_timeout: Duration,
-        idle_timeout: Duration,
-    ) -> Result<Vec<u8>, DtuAtError> {
-        self.read_until_idle_impl(first_timeout, idle_timeout, true)
-            .await
+    const FOUND: &[u8] = b"HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n";
+
+    #[test]
+    fn follows_redirect_to_final_response() {
+        let mock = redirect_hop(
+            get_script(FOUND),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ndone",
+        );
+        let mut client = DtuAtHttpClient::new(mock, redirect_config(3));
+        let resp = block_on(client.request(&get_request())).unwrap();
+        assert_eq!(resp.status_code, Some(200));
+        assert_eq!(resp.http_body(), Some(&b"done"[..]));
+        client.into_transport().assert_done();
     }
 
-    async fn read_until_idle_quiet(
-        &mut self,
-        first_timeout: Duration,
-        idle_timeout: Duration,
-    ) -> Result<Vec<u8>, DtuAtError> {
-        self.read_until_idle_impl(first_timeout, idle_timeout, false)
-            .await
+    #[test]
+    fn redirect_limit_is_enforced() {
+        let mock = redirect_hop(get_script(FOUND), FOUND);
+        let mut client = DtuAtHttpClient::new(mock, redirect_config(1));
+        assert_eq!(
+            block_on(client.request(&get_request())).unwrap_err(),
+            DtuAtError::TooManyRedirects
+        );
+        client.into_transport().assert_done();
     }
 
-    async fn read_until_idle_impl(
-        &mut self,
-        first_timeout: Duration,
-        idle_timeout: Duration,
-        log_first_timeout: bool,
-    ) -> Result<Vec<u8>, DtuAtError> {
-        let mut out = Vec::new();
-        let mut chunk = [0u8; 256];
-        let mut got_any = false;
+    #[test]
+    fn head_with_content_length_is_not_body_missing() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=HEAD\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\n\r\n");
+        let config = DtuAtHttpConfig {
+            wait_for_full_body: true,
+            require_body_on_success: true,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let req = HttpRequest::new(HttpMethod::Head, "http://example.com/");
+        let resp = block_on(client.request(&req)).unwrap();
+        assert_eq!(resp.declared_content_length(), Some(1234));
+        assert_eq!(resp.http_body(), Some(&b""[..]));
+        client.into_transport().assert_done();
+    }
 
-        loop {
-            let timeout = if got_any { idle_timeout } else { first_timeout };
-            let read_result =
-                with_timeout(timeout, AsyncRead::read(&mut self.transport, &mut chunk)).await;
+    #[test]
+    fn request_streaming_uploads_4k_reader() {
+        let body: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=POST\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/upload\r\n", OK)
+            .expect(b"AT+HTPHD1=Content-Length: 4096[0D][0A]\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(&body, b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n");
+        let config = DtuAtHttpConfig {
+            read_chunk_size: 512,
+            require_body_on_success: false,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let req = HttpRequest::new(HttpMethod::Post, "http://example.com/upload");
+        let mut reader = body.as_slice();
+        let resp = block_on(client.request_streaming(&req, &mut reader, body.len())).unwrap();
+        assert_eq!(resp.status_code, Some(201));
+        assert!(reader.is_empty());
+        client.into_transport().assert_done();
+    }
 
-            let n = match read_result {
-                Ok(result) => result.map_err(DtuAtError::Transport)?,
-                Err(_) => {
-                    if got_any {
-                        dtu_debug!(
-                            "dtu_http read idle timeout after receiving bytes, stop collecting"
-                        );
-                        break;
-                    }
-                    if log_first_timeout {
-                        dtu_warn!("dtu_http read first byte timeout");
-                    }
-                    return Err(DtuAtError::Timeout);
-                }
-            };
+    #[test]
+    fn long_header_block_is_split_across_htphd() {
+        let (a, b, c) = ("a".repeat(120), "b".repeat(120), "c".repeat(120));
+        let headers = [
+            HttpHeader::new("X-A", &a),
+            HttpHeader::new("X-B", &b),
+            HttpHeader::new("X-C", &c),
+        ];
+        let line = |name: &str, value: &str| format!("AT+HTPHD1={name}: {value}[0D][0A]\r\n");
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", OK)
+            .expect(line("X-A", &a).as_bytes(), OK)
+            .expect(line("X-B", &b).as_bytes(), OK)
+            .expect(line("X-C", &c).as_bytes(), OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        let config = DtuAtHttpConfig {
+            split_long_headers: true,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let req = get_request().with_headers(&headers);
+        block_on(client.request(&req)).unwrap();
+        client.into_transport().assert_done();
 
-            if n == 0 {
-                break;
+        // 未开启拆分：超限直接拒绝，不写 UART
+        let mut client = DtuAtHttpClient::new(MockTransport::new(), test_config());
+        assert!(matches!(
+            block_on(client.request(&req)),
+            Err(DtuAtError::InvalidConfig(_))
+        ));
+        assert!(client.into_transport().written().is_empty());
+    }
+
+    #[test]
+    fn url_length_limit_is_inclusive() {
+        let config = DtuAtHttpConfig {
+            max_url_len: 32,
+            ..test_config()
+        };
+        let client = DtuAtHttpClient::new(MockTransport::new(), config);
+        let at_limit = format!("http://example.com/{}", "p".repeat(13));
+        assert_eq!(at_limit.len(), 32);
+        let over = format!("{at_limit}x");
+
+        let req = HttpRequest::new(HttpMethod::Get, &at_limit);
+        assert_eq!(client.validate_request(&req), Ok(()));
+        let req = HttpRequest::new(HttpMethod::Get, &over);
+        assert!(matches!(
+            client.validate_request(&req),
+            Err(DtuAtError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn query_channel_config_reads_back_saved_values() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1?\r\n", b"\r\n+WKMOD1:HTTP\r\n\r\nOK\r\n")
+            .expect(b"AT+HTPTP1?\r\n", b"\r\n+HTPTP1:POST\r\n\r\nOK\r\n")
+            .expect(
+                b"AT+HTPURL1?\r\n",
+                b"\r\n+HTPURL1: \"http://example.com/api\"\r\n\r\nOK\r\n",
+            )
+            .expect(b"AT+HTPPK1?\r\n", b"\r\n+HTPPK1:3\r\n\r\nOK\r\n")
+            .expect(b"AT+HTPTIM1?\r\n", b"\r\n+HTPTIM1:10\r\n\r\nOK\r\n")
+            .expect(b"AT+HTPDT1?\r\n", b"\r\n+HTPDT1:QUERY\r\n\r\nOK\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let config = block_on(client.query_channel_config()).unwrap();
+        assert_eq!(
+            config,
+            ChannelConfig {
+                work_mode: String::from("HTTP"),
+                method: HttpMethod::Post,
+                url: String::from("http://example.com/api"),
+                response_filter_mask: 3,
+                request_timeout_secs: 10,
+                data_type: HttpDataType::Query,
             }
+        );
+        client.into_transport().assert_done();
+    }
 
-            got_any = true;
-            if out.len() + n > self.config.max_response_len {
-                return Err(DtuAtError::ResponseTooLarge);
+    #[test]
+    fn set_baud_writes_uart_command() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+UART=115200,8,1,NONE,NONE\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            client.set_baud(115200).await.unwrap();
+            for baud in [4800, 100_000, 1_000_000] {
+                assert!(matches!(
+                    client.set_baud(baud).await,
+                    Err(DtuAtError::InvalidConfig(_))
+                ));
             }
-            out.extend_from_slice(&chunk[..n]);
+        });
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_cap() {
+        let config = DtuAtHttpConfig {
+            retry_backoff_base: Duration::from_millis(100),
+            retry_backoff_max: Duration::from_millis(500),
+            ..test_config()
+        };
+        let delays: Vec<u64> = (1..=6)
+            .map(|retry| retry_backoff(&config, retry).as_millis())
+            .collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500, 500]);
+        // 极大的重试序号不会溢出
+        assert_eq!(retry_backoff(&config, u8::MAX), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn http_fail_is_not_retried_by_default() {
+        let mock = get_script(b"\r\nFS@HTTP FAIL:3\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        assert_eq!(client.config().max_request_attempts, 2);
+        assert_eq!(
+            block_on(client.request(&get_request())),
+            Err(DtuAtError::HttpFail(3))
+        );
+        // 只执行了一次请求流程，没有第二次 AT 探测
+        client.into_transport().assert_done();
+
+        assert!(!crate::types::RetryPolicy::TransientOnly.should_retry(&DtuAtError::HttpFail(3)));
+        assert!(
+            crate::types::RetryPolicy::IncludingHttpFail.should_retry(&DtuAtError::HttpFail(3))
+        );
+    }
+
+    #[test]
+    fn timing_phases_fit_within_total() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\n")
+            .then(Duration::from_millis(50), b"FS@HTTP OK:1\r\n")
+            .expect(b" ", b"")
+            .then(
+                Duration::from_millis(100),
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi",
+            );
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let (resp, timing) = block_on(client.request_timed(&get_request())).unwrap();
+        assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+
+        // 延时从脚本轮次匹配（写入）时起算，计时起点稍晚，并行测试下调度抖动更大，只取半数作下限
+        assert!(timing.save_wait_ms >= 25, "{timing:?}");
+        assert!(timing.first_byte_ms >= 50, "{timing:?}");
+        let phases = timing.enter_command_mode_ms
+            + timing.config_apply_ms
+            + timing.save_wait_ms
+            + timing.first_byte_ms;
+        assert!(phases <= timing.total_ms, "{timing:?}");
+    }
+
+    #[test]
+    fn total_budget_cuts_request_short() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        // 响应首字节在阶段超时（200ms）之内到达，但超出 100ms 的总预算
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"")
+            .then(
+                Duration::from_millis(150),
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi",
+            );
+        let config = DtuAtHttpConfig {
+            total_request_timeout: Some(Duration::from_millis(100)),
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let started = Instant::now();
+        assert_eq!(
+            block_on(client.request(&get_request())),
+            Err(DtuAtError::Timeout)
+        );
+        assert!(Instant::now() - started < Duration::from_secs(1));
+    }
+
+    static PROGRESS: std::sync::Mutex<Vec<(usize, usize)>> = std::sync::Mutex::new(Vec::new());
+
+    fn record_progress(sent: usize, total: usize) {
+        PROGRESS.lock().unwrap().push((sent, total));
+    }
+
+    #[test]
+    fn upload_progress_ends_with_total() {
+        let body = vec![b'x'; 1200];
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=POST\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(&body, b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        let config = DtuAtHttpConfig {
+            payload_chunk_size: 512,
+            auto_content_length: false,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        client.set_upload_progress(Some(record_progress));
+        let req = HttpRequest::new(HttpMethod::Post, "http://example.com/").with_body(&body);
+        block_on(client.request(&req)).unwrap();
+
+        assert_eq!(
+            *PROGRESS.lock().unwrap(),
+            [(512, 1200), (1024, 1200), (1200, 1200)]
+        );
+    }
+
+    #[test]
+    fn etag_revalidation_returns_not_modified() {
+        let first = b"HTTP/1.1 200 OK\r\nETag: W/\"v1\"\r\nContent-Length: 2\r\n\r\nhi";
+        let mock = get_script(first)
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", OK)
+            .expect(b"AT+HTPHD1=If-None-Match: W/\"v1\"[0D][0A]\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=BODY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 304 Not Modified\r\nETag: W/\"v1\"\r\n\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            let resp = client.request(&get_request()).await.unwrap();
+            let etag = resp.etag().unwrap();
+            assert_eq!(etag, "W/\"v1\"");
+
+            let req = get_request().with_if_none_match(&etag);
+            let resp = client.request(&req).await.unwrap();
+            assert!(resp.is_not_modified());
+            assert_eq!(resp.etag().as_deref(), Some("W/\"v1\""));
+        });
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn malformed_urls_are_invalid_url() {
+        let client = DtuAtHttpClient::new(MockTransport::new(), test_config());
+        for url in [
+            "",
+            "example.com/x",
+            "http:///x",
+            "http://[::1/x",
+            "http://example.com:99999/",
+            "http://exa mple.com/",
+            "http://example.com/\r\nAT+Z",
+        ] {
+            let req = HttpRequest::new(HttpMethod::Get, url);
+            assert!(
+                matches!(
+                    client.validate_request(&req),
+                    Err(DtuAtError::InvalidUrl(_))
+                ),
+                "{url:?}"
+            );
         }
+    }
 
-        Ok(out)
+    #[test]
+    fn custom_command_mode_sequence_is_written() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", b"")
+            .expect(b"$$$", OK)
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 20,99\r\n\r\nOK\r\n");
+        let config = DtuAtHttpConfig {
+            command_mode_sequence: b"$$$",
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        assert_eq!(block_on(client.query_signal_strength()), Ok(-73));
+        let mock = client.into_transport();
+        mock.assert_done();
+        assert!(find_subslice(mock.written(), b"+++").is_none());
     }
-}
 
-fn short_poll_timeout(base: Duration) -> Duration {
-    if base.as_millis() > 800 {
-        Duration::from_millis(800)
-    } else {
-        base
+    #[test]
+    fn custom_line_terminator_is_used() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r", OK)
+            .expect(b"AT+CSQ\r", b"\r\n+CSQ: 20,99\r\n\r\nOK\r\n");
+        let config = DtuAtHttpConfig {
+            line_terminator: b"\r",
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        assert_eq!(block_on(client.query_signal_strength()), Ok(-73));
+        let mock = client.into_transport();
+        mock.assert_done();
+        assert!(!mock.written().contains(&b'\n'));
     }
-}
 
-fn log_response_preview(tag: &'static str, buf: &[u8]) {
-    let preview_len = core::cmp::min(160, buf.len());
-    let preview = &buf[..preview_len];
+    #[test]
+    fn link_status_keeps_partial_results() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CREG?\r\n", b"\r\n+CREG: 0,1\r\n\r\nOK\r\n")
+            .expect(b"AT+CSQ\r\n", b"\r\nERROR\r\n")
+            .expect(b"AT+RUNST?\r\n", b"\r\n+RUNST:CONNECTED\r\n\r\nOK\r\n")
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CREG?\r\n", b"\r\n+CREG: 0,5\r\n\r\nOK\r\n")
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 31,99\r\n\r\nOK\r\n")
+            .expect(b"AT+RUNST?\r\n", b"\r\n+CME ERROR: 4\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            assert_eq!(
+                client.query_link_status().await.unwrap(),
+                LinkStatus {
+                    registration: RegistrationState::Registered,
+                    rssi_dbm: None,
+                    run_state: Some(String::from("CONNECTED")),
+                }
+            );
+            assert_eq!(
+                client.query_link_status().await.unwrap(),
+                LinkStatus {
+                    registration: RegistrationState::Roaming,
+                    rssi_dbm: Some(-51),
+                    run_state: None,
+                }
+            );
+        });
+        client.into_transport().assert_done();
+    }
 
-    if let Ok(text) = core::str::from_utf8(preview) {
-        dtu_debug!("dtu_http {} rsp_len={}, preview={}", tag, buf.len(), text);
-    } else {
-        let hex = bytes_to_hex(preview);
-        dtu_debug!(
-            "dtu_http {} rsp_len={}, preview_hex={}",
-            tag,
-            buf.len(),
-            hex
+    #[test]
+    fn weak_signal_blocks_send() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        // rssi=1 → -111dBm，低于 -100dBm 阈值
+        let mock = channel_setup(mock)
+            .expect(b"AT+CREG?\r\n", b"\r\n+CREG: 0,1\r\n\r\nOK\r\n")
+            .expect(b"AT+CSQ\r\n", b"\r\n+CSQ: 1,99\r\n\r\nOK\r\n")
+            .expect(b"AT+RUNST?\r\n", b"\r\n+RUNST:CONNECTED\r\n\r\nOK\r\n");
+        let config = DtuAtHttpConfig {
+            query_link_status_before_send: true,
+            min_signal_dbm: Some(-100),
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        assert_eq!(
+            block_on(client.request(&get_request())),
+            Err(DtuAtError::WeakSignal(-111))
         );
+        assert_eq!(client.last_step(), Some("link_status"));
+        let mock = client.into_transport();
+        mock.assert_done();
+        assert!(find_subslice(mock.written(), b"AT+S\r\n").is_none());
     }
-}
 
-fn bytes_to_hex(data: &[u8]) -> String {
-    let mut out = String::new();
-    for (idx, b) in data.iter().enumerate() {
-        if idx > 0 {
-            let _ = out.write_str(" ");
+    #[test]
+    fn modem_http_timeout_is_its_own_error() {
+        let mock = get_script(b"\r\nFS@HTTP TIMEOUT\r\n");
+        let config = DtuAtHttpConfig {
+            max_request_attempts: 1,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        let err = block_on(client.request(&get_request())).unwrap_err();
+        assert_eq!(err, DtuAtError::ModemTimeout);
+        assert_ne!(err, DtuAtError::Timeout);
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn wait_for_marker_matches_and_times_out() {
+        let mut mock = MockTransport::new();
+        mock.push_read(b"\r\n+CEREG: 1\r\n");
+        mock.push_read(b"\r\n+CME ERROR: 30\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            let got = client
+                .wait_for_marker(b"+CME ERROR", Duration::from_millis(200))
+                .await
+                .unwrap();
+            assert_eq!(got, b"\r\n+CEREG: 1\r\n\r\n+CME ERROR: 30\r\n");
+
+            let started = Instant::now();
+            assert_eq!(
+                client
+                    .wait_for_marker(b"FS@HTTP", Duration::from_millis(100))
+                    .await,
+                Err(DtuAtError::Timeout)
+            );
+            assert!(Instant::now() - started >= Duration::from_millis(100));
+
+            assert!(matches!(
+                client
+                    .wait_for_marker(b"", Duration::from_millis(100))
+                    .await,
+                Err(DtuAtError::InvalidConfig(_))
+            ));
+        });
+        assert!(client.into_transport().written().is_empty());
+    }
+
+    #[test]
+    fn query_data_type_sends_encoded_pairs() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", OK)
+            .expect(b"AT+HTPPK1=3\r\n", OK)
+            .expect(b"AT+HTPTIM1=10\r\n", OK)
+            .expect(b"AT+HTPDT1=QUERY\r\n", OK)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            // 模块把 payload 拼接到 URL 之后：GET http://example.com/?id=7&name=a%20b
+            .expect(
+                b"id=7&name=a%20b",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+            );
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let pairs = [("id", "7"), ("name", "a b")];
+        let req = get_request().with_body(b"ignored").with_query_pairs(&pairs);
+        let resp = block_on(client.request(&req)).unwrap();
+        assert_eq!(resp.http_body(), Some(&b"ok"[..]));
+        client.into_transport().assert_done();
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless_response_limit_is_exact() {
+        const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi";
+        assert_eq!(RESPONSE.len(), 40);
+
+        let mut client = DtuAtHttpClient::new(get_script(RESPONSE), test_config());
+        let resp = block_on(client.request_heapless::<40>(&get_request())).unwrap();
+        assert_eq!(resp.raw.len(), 40);
+        assert_eq!(resp.as_ref().http_body(), Some(&b"hi"[..]));
+        client.into_transport().assert_done();
+
+        let mut client = DtuAtHttpClient::new(get_script(RESPONSE), test_config());
+        assert!(matches!(
+            block_on(client.request_heapless::<39>(&get_request())),
+            Err(DtuAtError::ResponseTooLarge)
+        ));
+    }
+
+    #[test]
+    fn recycled_response_buffer_is_reused() {
+        let mock = get_script(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none")
+            .expect(b"AT\r\n", b"")
+            .expect(b"+++", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK);
+        let mock = channel_setup(mock)
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+
+        block_on(async {
+            let first = client.request(&get_request()).await.unwrap();
+            let (ptr, capacity) = (first.raw.as_ptr(), first.raw.capacity());
+            client.recycle_response(first);
+
+            let second = client.request(&get_request()).await.unwrap();
+            assert_eq!(second.http_body(), Some(&b"two"[..]));
+            // 第二次请求直接读入归还的缓冲，没有重新分配或扩容
+            assert_eq!(second.raw.as_ptr(), ptr);
+            assert_eq!(second.raw.capacity(), capacity);
+        });
+        client.into_transport().assert_done();
+    }
+
+    #[test]
+    fn read_response_collects_late_reply() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CSQ\r\n", OK)
+            .then(Duration::from_millis(60), b"\r\n+CSQ: 20,99\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            assert_eq!(client.send_command("AT+CSQ").await.unwrap(), OK);
+            let late = client
+                .read_response(Duration::from_millis(200), Duration::from_millis(20))
+                .await
+                .unwrap();
+            assert_eq!(late, b"\r\n+CSQ: 20,99\r\n");
+            assert_eq!(
+                client
+                    .read_response(Duration::from_millis(50), Duration::from_millis(20))
+                    .await,
+                Err(DtuAtError::Timeout)
+            );
+        });
+        let mock = client.into_transport();
+        mock.assert_done();
+        assert_eq!(mock.written(), b"AT\r\nAT+CSQ\r\n");
+    }
+
+    #[test]
+    fn run_sequence_stops_at_first_failure() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+CMEE=1\r\n", OK)
+            .expect(b"AT+CGATT=1\r\n", OK)
+            .expect(b"AT+HTPSSL1=0\r\n", b"\r\nERROR\r\n");
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        let cmds = ["AT+CMEE=1", "AT+CGATT=1", "AT+HTPSSL1=0", "AT+S"];
+        assert_eq!(
+            block_on(client.run_sequence(&cmds)),
+            Err((2, DtuAtError::AtRejected))
+        );
+        let mock = client.into_transport();
+        mock.assert_done();
+        assert!(find_subslice(mock.written(), b"AT+S").is_none());
+
+        // 任一命令格式不合法时一条都不发送
+        let mut client = DtuAtHttpClient::new(MockTransport::new(), test_config());
+        assert!(matches!(
+            block_on(client.run_sequence(&["AT+CMEE=1", "AT\r\nAT+Z"])),
+            Err((1, DtuAtError::InvalidConfig(_)))
+        ));
+        assert!(client.into_transport().written().is_empty());
+    }
+
+    #[test]
+    fn failed_step_is_reported() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+WKMOD1=HTTP\r\n", OK)
+            .expect(b"AT+HTPTP1=GET\r\n", OK)
+            .expect(
+                b"AT+HTPURL1=http://example.com/\r\n",
+                b"\r\n+CME ERROR: 50\r\n",
+            );
+        let config = DtuAtHttpConfig {
+            max_request_attempts: 1,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(mock, config);
+        assert_eq!(client.last_step(), None);
+        assert_eq!(
+            block_on(client.request(&get_request())),
+            Err(DtuAtError::CmeError(50))
+        );
+        assert_eq!(client.last_step(), Some("HTPURL"));
+        client.into_transport().assert_done();
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn recent_exchanges_evict_oldest() {
+        let config = DtuAtHttpConfig {
+            recent_exchanges_len: 3,
+            ..test_config()
+        };
+        let mut client = DtuAtHttpClient::new(MockTransport::new(), config);
+        for cmd in ["AT+A", "AT+B", "AT+C", "AT+D"] {
+            client.record_exchange(cmd, OK);
         }
-        let _ = write!(&mut out, "{:02X}", b);
+        let cmds: Vec<&str> = client.recent_exchanges().map(|(cmd, _)| cmd).collect();
+        assert_eq!(cmds, ["AT+B", "AT+C", "AT+D"]);
+
+        client.record_exchange("AT+E", &[b'x'; 100]);
+        let (cmd, rsp) = client.recent_exchanges().last().unwrap();
+        assert_eq!(cmd, "AT+E");
+        assert_eq!(rsp.len(), RECENT_RESPONSE_MAX);
+        assert_eq!(client.recent_exchanges().count(), 3);
+    }
+
+    #[test]
+    fn non_http_schemes_are_rejected_before_writing() {
+        let mut client = DtuAtHttpClient::new(MockTransport::new(), test_config());
+        for url in ["ftp://example.com/file", "mqtt://broker.example.com:1883/t"] {
+            let req = HttpRequest::new(HttpMethod::Get, url);
+            assert!(
+                matches!(
+                    block_on(client.request(&req)),
+                    Err(DtuAtError::InvalidUrl(_))
+                ),
+                "{url:?}"
+            );
+        }
+        assert!(client.into_transport().written().is_empty());
+    }
+
+    #[test]
+    fn tls_verify_modes_write_htpssl() {
+        let mock = MockTransport::new()
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+HTPSSL1=0\r\n", OK)
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+HTPSSL1=1\r\n", OK)
+            .expect(b"AT\r\n", OK)
+            .expect(b"AT+HTPSSL1=2\r\n", OK);
+        let mut client = DtuAtHttpClient::new(mock, test_config());
+        block_on(async {
+            for mode in [
+                TlsVerify::None,
+                TlsVerify::ServerCert,
+                TlsVerify::MutualAuth,
+            ] {
+                client.set_tls_verify(mode).await.unwrap();
+            }
+        });
+        client.into_transport().assert_done();
     }
-    out
 }
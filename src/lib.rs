@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 // ── feature 合法性校验 ─────────────────────────────────────────────────────
 #[cfg(not(any(
@@ -19,11 +19,26 @@ extern crate alloc;
 mod client;
 #[macro_use]
 pub(crate) mod dbglog;
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+mod multipart;
+mod observer;
 mod parser;
 mod types;
 mod util;
 
-pub use client::DtuAtHttpClient;
+#[cfg(target_os = "none")]
+pub use client::DtuUartClient;
+pub use client::{CommandSession, DtuAtHttpClient};
+#[cfg(feature = "test-util")]
+pub use mock::MockTransport;
+pub use multipart::MultipartBuilder;
+pub use observer::AtObserver;
+#[cfg(feature = "heapless")]
+pub use types::HttpResponseN;
 pub use types::{
-    DtuAtError, DtuAtHttpConfig, HttpDataType, HttpHeader, HttpMethod, HttpRequest, HttpResponse,
+    CertKind, ChannelConfig, DEFAULT_ERROR_TOKENS, DEFAULT_OK_TOKENS, DEFAULT_TERMINAL_MARKERS,
+    DtuAtError, DtuAtHttpConfig, DtuAtHttpConfigBuilder, HttpDataType, HttpHeader, HttpMethod,
+    HttpRequest, HttpResponse, HttpResponseRef, LinkStatus, Markers, NetworkTime,
+    RegistrationState, RequestTiming, RetryPolicy, StatusClass, StreamedResponse, TlsVerify,
 };
@@ -0,0 +1,212 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use core::convert::Infallible;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async::{ErrorType, Read, Write};
+
+/// 脚本化的内存传输层，用于在主机侧驱动 [`DtuAtHttpClient`](crate::DtuAtHttpClient) 的完整状态机
+/// （需 `test-util` feature）。
+///
+/// 脚本由若干轮 `(期望写入, 预置读出)` 组成：客户端写入的字节累计匹配到当前轮的期望写入后，
+/// 该轮的预置读出进入读队列，供后续 `read` 依次返回。写入与期望不符时直接 panic。
+///
+/// 读队列为空时 `read` 永不完成，由客户端的 `with_timeout` 触发超时，
+/// 因此预置读出为空即可模拟“模块无响应”。[`then`](Self::then) 可让同一轮的后续数据延时到达。
+/// 主机侧运行需为 `embassy-time` 提供时间驱动。
+///
+/// ```rust,ignore
+/// let transport = MockTransport::new()
+///     .expect(b"AT\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+WKMOD1=HTTP\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+HTPTP1=GET\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+HTPURL1=http://example.com/\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+HTPPK1=3\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+HTPTIM1=10\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+HTPDT1=BODY\r\n", b"\r\nOK\r\n")
+///     .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+///     .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi");
+///
+/// let mut client = DtuAtHttpClient::new(transport, DtuAtHttpConfig::default());
+/// let resp = client.send(HttpMethod::Get, "http://example.com/", &[], &[]).await?;
+/// assert_eq!(resp.status_code, Some(200));
+/// assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+/// client.into_transport().assert_done();
+/// ```
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    turns: VecDeque<Turn>,
+    pending_write: Vec<u8>,
+    reads: VecDeque<(Instant, Vec<u8>)>,
+    written: Vec<u8>,
+}
+
+/// 一轮脚本：期望写入，以及依次可读出的 `(相对前一段的延时, 数据)`。
+type Turn = (Vec<u8>, Vec<(Duration, Vec<u8>)>);
+
+impl MockTransport {
+    /// 创建空脚本。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一轮：期望客户端写入 `write`，匹配后可读出 `read`（为空表示无响应）。
+    pub fn expect(mut self, write: &[u8], read: &[u8]) -> Self {
+        self.turns.push_back((
+            write.to_vec(),
+            alloc::vec![(Duration::from_ticks(0), read.to_vec())],
+        ));
+        self
+    }
+
+    /// 为上一轮追加一段延时读出：该轮前一段读出可用 `delay` 之后，`read` 才能被读到
+    /// （模拟响应分段到达）。
+    ///
+    /// # Panics
+    /// 尚未调用过 [`expect`](Self::expect) 时 panic。
+    pub fn then(mut self, delay: Duration, read: &[u8]) -> Self {
+        let (_, reads) = self
+            .turns
+            .back_mut()
+            .expect("MockTransport: then() without a preceding expect()");
+        reads.push((delay, read.to_vec()));
+        self
+    }
+
+    /// 直接向读队列追加一段数据（模拟主动上报的 URC 等，不依赖写入）。
+    pub fn push_read(&mut self, read: &[u8]) {
+        self.push_read_at(Instant::now(), read);
+    }
+
+    fn push_read_at(&mut self, at: Instant, read: &[u8]) {
+        if !read.is_empty() {
+            self.reads.push_back((at, read.to_vec()));
+        }
+    }
+
+    /// 客户端累计写入的全部字节。
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// 脚本是否已全部执行完毕。
+    pub fn is_done(&self) -> bool {
+        self.turns.is_empty() && self.pending_write.is_empty()
+    }
+
+    /// 断言脚本已全部执行完毕，否则 panic 并指出剩余轮数。
+    pub fn assert_done(&self) {
+        assert!(
+            self.is_done(),
+            "MockTransport: {} scripted turn(s) not consumed",
+            self.turns.len()
+        );
+    }
+}
+
+impl ErrorType for MockTransport {
+    type Error = Infallible;
+}
+
+impl Read for MockTransport {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let Some((at, front)) = self.reads.front_mut() else {
+            // 无数据：挂起，交由调用方超时
+            return core::future::pending().await;
+        };
+        // 按绝对时间等待，被调用方超时取消后再次读取仍以同一时刻为准
+        Timer::at(*at).await;
+
+        let n = core::cmp::min(buf.len(), front.len());
+        buf[..n].copy_from_slice(&front[..n]);
+        front.drain(..n);
+        if front.is_empty() {
+            self.reads.pop_front();
+        }
+        Ok(n)
+    }
+}
+
+impl Write for MockTransport {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.written.extend_from_slice(buf);
+        self.pending_write.extend_from_slice(buf);
+
+        let Some((expected, _)) = self.turns.front() else {
+            panic!(
+                "MockTransport: unexpected write {:?}",
+                core::str::from_utf8(&self.pending_write)
+            );
+        };
+        assert!(
+            expected.starts_with(&self.pending_write),
+            "MockTransport: write mismatch, expected {:?}, got {:?}",
+            core::str::from_utf8(expected),
+            core::str::from_utf8(&self.pending_write)
+        );
+
+        if self.pending_write.len() == expected.len() {
+            if let Some((_, reads)) = self.turns.pop_front() {
+                let mut at = Instant::now();
+                for (delay, read) in reads {
+                    at += delay;
+                    self.push_read_at(at, &read);
+                }
+            }
+            self.pending_write.clear();
+        }
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// 主机测试用配置：缩短各类超时，使脚本中的“无响应”轮次很快结束。
+#[cfg(test)]
+pub(crate) fn test_config() -> crate::DtuAtHttpConfig {
+    use embassy_time::Duration;
+
+    crate::DtuAtHttpConfig {
+        cmd_guard_time: Duration::from_millis(1),
+        at_first_timeout: Duration::from_millis(50),
+        at_idle_timeout: Duration::from_millis(10),
+        http_first_timeout: Duration::from_millis(200),
+        http_idle_timeout: Duration::from_millis(20),
+        http_followup_first_timeout: Duration::from_millis(20),
+        http_followup_timeout: Duration::from_millis(100),
+        http_ready_timeout: Duration::from_millis(200),
+        enter_cmd_timeout: Duration::from_millis(500),
+        enter_cmd_poll: Duration::from_millis(10),
+        post_entm_settle_time: Duration::from_millis(1),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DtuAtHttpClient, HttpMethod};
+    use embassy_futures::block_on;
+
+    #[test]
+    fn drives_full_get_flow() {
+        let transport = MockTransport::new()
+            .expect(b"AT\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+WKMOD1=HTTP\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+HTPTP1=GET\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+HTPURL1=http://example.com/\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+HTPPK1=3\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+HTPTIM1=10\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+HTPDT1=BODY\r\n", b"\r\nOK\r\n")
+            .expect(b"AT+S\r\n", b"\r\nOK\r\nFS@HTTP OK:1\r\n")
+            .expect(b" ", b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi");
+
+        let mut client = DtuAtHttpClient::new(transport, test_config());
+        let resp = block_on(client.send(HttpMethod::Get, "http://example.com/", &[], &[])).unwrap();
+        assert_eq!(resp.status_code, Some(200));
+        assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+        client.into_transport().assert_done();
+    }
+}
@@ -0,0 +1,168 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// boundary 序号。部分 RISC-V 目标（如 ESP32-C2/C3）不支持原子 RMW，
+/// 这里只用 load/store；极端并发下序号重复也不影响正确性（boundary 仅需不出现在内容中）。
+static BOUNDARY_SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// `multipart/form-data` 请求体构造器。
+///
+/// 支持任意个文本字段与一个二进制文件字段。请求体整体在内存中拼接后经 UART 透传发送，
+/// 实际大小受模块透传缓冲与 `request_timeout_secs` 约束，建议控制在数 KB 以内。
+#[derive(Debug, Clone)]
+pub struct MultipartBuilder<'a> {
+    boundary: String,
+    texts: Vec<(&'a str, &'a str)>,
+    file: Option<MultipartFile<'a>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MultipartFile<'a> {
+    name: &'a str,
+    filename: &'a str,
+    content_type: &'a str,
+    data: &'a [u8],
+}
+
+impl<'a> MultipartBuilder<'a> {
+    /// 创建构造器，boundary 由进程内递增序号生成（确定且互不相同）。
+    pub fn new() -> Self {
+        let seq = BOUNDARY_SEQ.load(Ordering::Relaxed).wrapping_add(1);
+        BOUNDARY_SEQ.store(seq, Ordering::Relaxed);
+        Self::with_boundary_seed(seq)
+    }
+
+    /// 使用指定种子生成 boundary（便于测试得到稳定输出）。
+    pub fn with_boundary_seed(seed: u32) -> Self {
+        Self {
+            boundary: format!("----DtuFormBoundary{:08X}", seed),
+            texts: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// 当前 boundary。
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// 追加一个文本字段。
+    pub fn text(mut self, name: &'a str, value: &'a str) -> Self {
+        self.texts.push((name, value));
+        self
+    }
+
+    /// 设置二进制文件字段（仅支持一个，重复调用时覆盖）。
+    pub fn file(
+        mut self,
+        name: &'a str,
+        filename: &'a str,
+        content_type: &'a str,
+        data: &'a [u8],
+    ) -> Self {
+        self.file = Some(MultipartFile {
+            name,
+            filename,
+            content_type,
+            data,
+        });
+        self
+    }
+
+    /// `Content-Type` 头的值：`multipart/form-data; boundary=...`。
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// 生成 `(Content-Type 头的值, 请求体)`。
+    pub fn build(&self) -> (String, Vec<u8>) {
+        let mut body = Vec::new();
+
+        for (name, value) in &self.texts {
+            self.push_part_head(&mut body);
+            body.extend_from_slice(b"Content-Disposition: form-data; name=\"");
+            push_quoted(&mut body, name);
+            body.extend_from_slice(b"\"\r\n\r\n");
+            body.extend_from_slice(value.as_bytes());
+            body.extend_from_slice(b"\r\n");
+        }
+
+        if let Some(file) = &self.file {
+            self.push_part_head(&mut body);
+            body.extend_from_slice(b"Content-Disposition: form-data; name=\"");
+            push_quoted(&mut body, file.name);
+            body.extend_from_slice(b"\"; filename=\"");
+            push_quoted(&mut body, file.filename);
+            body.extend_from_slice(b"\"\r\nContent-Type: ");
+            body.extend_from_slice(file.content_type.as_bytes());
+            body.extend_from_slice(b"\r\n\r\n");
+            body.extend_from_slice(file.data);
+            body.extend_from_slice(b"\r\n");
+        }
+
+        body.extend_from_slice(b"--");
+        body.extend_from_slice(self.boundary.as_bytes());
+        body.extend_from_slice(b"--\r\n");
+
+        (self.content_type(), body)
+    }
+
+    fn push_part_head(&self, body: &mut Vec<u8>) {
+        body.extend_from_slice(b"--");
+        body.extend_from_slice(self.boundary.as_bytes());
+        body.extend_from_slice(b"\r\n");
+    }
+}
+
+impl Default for MultipartBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 字段名 / 文件名中的 `"` 与换行按 HTML 表单规则编码，避免破坏头部结构。
+fn push_quoted(body: &mut Vec<u8>, value: &str) {
+    for &b in value.as_bytes() {
+        match b {
+            b'"' => body.extend_from_slice(b"%22"),
+            b'\r' => body.extend_from_slice(b"%0D"),
+            b'\n' => body.extend_from_slice(b"%0A"),
+            _ => body.push(b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_byte_exact_form_data() {
+        let (content_type, body) = MultipartBuilder::with_boundary_seed(0x2A)
+            .text("device", "dtu-01")
+            .text("say \"hi\"", "a\r\nb")
+            .file("log", "boot.bin", "application/octet-stream", &[0x00, 0xFF])
+            .build();
+
+        assert_eq!(
+            content_type,
+            "multipart/form-data; boundary=----DtuFormBoundary0000002A"
+        );
+        let mut expected = b"------DtuFormBoundary0000002A\r\n\
+Content-Disposition: form-data; name=\"device\"\r\n\r\n\
+dtu-01\r\n\
+------DtuFormBoundary0000002A\r\n\
+Content-Disposition: form-data; name=\"say %22hi%22\"\r\n\r\n\
+a\r\nb\r\n\
+------DtuFormBoundary0000002A\r\n\
+Content-Disposition: form-data; name=\"log\"; filename=\"boot.bin\"\r\n\
+Content-Type: application/octet-stream\r\n\r\n"
+            .to_vec();
+        expected.extend_from_slice(&[0x00, 0xFF]);
+        expected.extend_from_slice(b"\r\n------DtuFormBoundary0000002A--\r\n");
+        assert_eq!(body, expected);
+    }
+}
@@ -0,0 +1,20 @@
+use crate::types::DtuAtError;
+
+/// AT 交互观察者：在不开启调试日志的情况下捕获命令 / 响应，用于自建环形缓冲或遥测。
+///
+/// 所有方法默认空实现，按需覆盖。回调在收发路径上同步执行，应保持轻量、不阻塞。
+pub trait AtObserver {
+    /// 即将发送的 AT 命令（不含结尾 `\r\n`），包括 `+++` 与 `AT` 探测。
+    ///
+    /// 开启 `redact_secrets` 时 `Authorization` 头的凭据已替换为 `***`。
+    fn on_command(&mut self, _cmd: &str) {}
+
+    /// 即将发送的 HTTP payload（请求体原始字节）。
+    fn on_payload(&mut self, _payload: &[u8]) {}
+
+    /// 收到的原始响应（AT 响应或 HTTP 响应），包括 `+++` 与 `AT` 探测的回复。
+    fn on_response(&mut self, _rsp: &[u8]) {}
+
+    /// 命令或响应处理出错。
+    fn on_error(&mut self, _err: &DtuAtError) {}
+}
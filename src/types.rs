@@ -1,16 +1,25 @@
+use alloc::borrow::Cow;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 use embassy_time::Duration;
-use esp_hal::uart::IoError;
+use embedded_io_async::ErrorKind;
 
-use crate::util::find_subslice;
+use crate::util::{encode_query_pairs, find_final_status_line, find_subslice};
 
-/// HTTP 请求方法（DTU 指令集仅支持 GET / POST）。
+/// HTTP 请求方法。
+///
+/// DTU 指令集文档仅列出 GET / POST；PUT / PATCH / DELETE / HEAD 依赖固件支持，
+/// 固件拒绝时返回 [`DtuAtError::UnsupportedMethod`]。
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HttpMethod {
     Get,
     Post,
+    Put,
+    Patch,
+    Delete,
+    /// 只取响应头；即使开启 `require_body_on_success` 也不要求 body。
+    Head,
 }
 
 impl HttpMethod {
@@ -18,8 +27,25 @@ impl HttpMethod {
         match self {
             Self::Get => "GET",
             Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+            Self::Head => "HEAD",
         }
     }
+
+    pub(crate) fn from_at(s: &str) -> Option<Self> {
+        [
+            Self::Get,
+            Self::Post,
+            Self::Put,
+            Self::Patch,
+            Self::Delete,
+            Self::Head,
+        ]
+        .into_iter()
+        .find(|m| m.as_at().eq_ignore_ascii_case(s))
+    }
 }
 
 /// 串口数据类型，对应 `AT+HTPDT`。
@@ -36,6 +62,170 @@ impl HttpDataType {
             Self::Query => "QUERY",
         }
     }
+
+    pub(crate) fn from_at(s: &str) -> Option<Self> {
+        [Self::Body, Self::Query]
+            .into_iter()
+            .find(|t| t.as_at().eq_ignore_ascii_case(s))
+    }
+}
+
+/// 模块中已保存的 HTTP 通道参数（由 `query_channel_config` 读回）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelConfig {
+    /// 工作模式（`AT+WKMOD`），HTTP 通道应为 `"HTTP"`。
+    pub work_mode: String,
+    /// 请求方法（`AT+HTPTP`）。
+    pub method: HttpMethod,
+    /// 请求 URL（`AT+HTPURL`）。
+    pub url: String,
+    /// 响应过滤位（`AT+HTPPK`）。
+    pub response_filter_mask: u8,
+    /// 请求超时秒数（`AT+HTPTIM`）。
+    pub request_timeout_secs: u16,
+    /// 串口数据类型（`AT+HTPDT`）。
+    pub data_type: HttpDataType,
+}
+
+/// 网络注册状态，对应 `+CREG: <n>,<stat>` 中的 `<stat>`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationState {
+    /// 0：未注册，且未在搜索网络。
+    NotRegistered,
+    /// 1：已注册本地网络。
+    Registered,
+    /// 2：未注册，正在搜索网络。
+    Searching,
+    /// 3：注册被拒绝。
+    Denied,
+    /// 4 或其他未定义值：状态未知。
+    Unknown,
+    /// 5：已注册，漫游。
+    Roaming,
+}
+
+impl RegistrationState {
+    pub(crate) fn from_stat(stat: u8) -> Self {
+        match stat {
+            0 => Self::NotRegistered,
+            1 => Self::Registered,
+            2 => Self::Searching,
+            3 => Self::Denied,
+            5 => Self::Roaming,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// 是否已注册（本地或漫游），可以发起数据业务。
+    pub fn is_registered(self) -> bool {
+        matches!(self, Self::Registered | Self::Roaming)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotRegistered => "not registered",
+            Self::Registered => "registered",
+            Self::Searching => "searching",
+            Self::Denied => "denied",
+            Self::Unknown => "unknown",
+            Self::Roaming => "roaming",
+        }
+    }
+}
+
+/// 链路状态快照（由 [`DtuAtHttpClient::query_link_status`](crate::DtuAtHttpClient::query_link_status) 返回）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkStatus {
+    /// 网络注册状态（`AT+CREG?`）。
+    pub registration: RegistrationState,
+    /// 信号强度（`AT+CSQ` 换算的 dBm），未知或查询失败时为 `None`。
+    pub rssi_dbm: Option<i16>,
+    /// 模块运行状态（`AT+RUNST?` 的值，原样保留），查询失败时为 `None`。
+    pub run_state: Option<String>,
+}
+
+/// 蜂窝网络下发的本地时间（由 `query_network_time` 解析 `AT+CCLK?` 得到）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkTime {
+    /// 四位年份（两位年份按 `2000 + yy` 换算）。
+    pub year: u16,
+    /// 1~12。
+    pub month: u8,
+    /// 1~31。
+    pub day: u8,
+    /// 0~23。
+    pub hour: u8,
+    /// 0~59。
+    pub minute: u8,
+    /// 0~59。
+    pub second: u8,
+    /// 本地时间相对 UTC 的偏移，单位为刻钟（15 分钟），东区为正，如 `+32` 表示 UTC+8。
+    pub tz_quarters: i8,
+}
+
+impl NetworkTime {
+    /// 相对 UTC 的偏移分钟数（`tz_quarters * 15`）。
+    pub fn utc_offset_minutes(&self) -> i16 {
+        i16::from(self.tz_quarters) * 15
+    }
+}
+
+/// HTTPS 证书校验模式，对应 `AT+HTPSSL<ch>=<n>`（由 `set_tls_verify` 下发）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsVerify {
+    /// 0：不校验服务器证书（仅加密，易受中间人攻击）。
+    None,
+    /// 1：用模块中预置的 CA 证书校验服务器证书。
+    ServerCert,
+    /// 2：双向认证：校验服务器证书并出示客户端证书。
+    MutualAuth,
+}
+
+impl TlsVerify {
+    pub(crate) fn as_at(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::ServerCert => 1,
+            Self::MutualAuth => 2,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::ServerCert => "server cert",
+            Self::MutualAuth => "mutual auth",
+        }
+    }
+}
+
+/// 证书槽内容类型，对应 `AT+SSLCERT=<slot>,<kind>,<len>` 中的 `<kind>`（由 `upload_certificate` 下发）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertKind {
+    /// 0：CA 根证书，用于校验服务器。
+    Ca,
+    /// 1：客户端证书（双向认证）。
+    ClientCert,
+    /// 2：客户端私钥（双向认证）。
+    ClientKey,
+}
+
+impl CertKind {
+    pub(crate) fn as_at(self) -> u8 {
+        match self {
+            Self::Ca => 0,
+            Self::ClientCert => 1,
+            Self::ClientKey => 2,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ca => "ca",
+            Self::ClientCert => "client cert",
+            Self::ClientKey => "client key",
+        }
+    }
 }
 
 /// HTTP 头键值。
@@ -65,6 +255,20 @@ pub struct HttpRequest<'a> {
     pub body: &'a [u8],
     pub bearer_token: Option<&'a str>,
     pub data_type: HttpDataType,
+    /// 追加到 URL 的查询参数（发送时百分号编码）。
+    pub query: &'a [(&'a str, &'a str)],
+    /// 覆盖 [`DtuAtHttpConfig::http_first_timeout`]，`None` 时使用配置值。
+    pub http_first_timeout: Option<Duration>,
+    /// 请求字节范围 `(start, end)`，发送时生成 `Range: bytes=<start>-<end>` 头；`end` 为闭区间。
+    pub range: Option<(u64, Option<u64>)>,
+    /// 条件请求的 ETag，发送时生成 `If-None-Match` 头。
+    pub if_none_match: Option<&'a str>,
+    /// HTTP Basic 认证的 `(用户名, 密码)`，发送时编码为 `Authorization: Basic <base64>`。
+    pub basic_auth: Option<(&'a str, &'a str)>,
+    /// `QUERY` 数据类型的查询参数，非空时编码后代替 `body` 作为透传 payload。
+    pub query_payload: &'a [(&'a str, &'a str)],
+    /// 发送时按键排序 `query`（见 [`with_sorted_query`](Self::with_sorted_query)）。
+    pub sort_query: bool,
 }
 
 impl<'a> HttpRequest<'a> {
@@ -77,6 +281,13 @@ impl<'a> HttpRequest<'a> {
             body: &[],
             bearer_token: None,
             data_type: HttpDataType::Body,
+            query: &[],
+            http_first_timeout: None,
+            range: None,
+            if_none_match: None,
+            basic_auth: None,
+            query_payload: &[],
+            sort_query: false,
         }
     }
 
@@ -98,11 +309,144 @@ impl<'a> HttpRequest<'a> {
         self
     }
 
+    /// 设置 HTTP Basic 认证（会拼接为 `Authorization: Basic <base64(user:pass)>` 头）。
+    ///
+    /// 为保持 `HttpRequest` 的 `Copy` 与 `const fn` 构造，这里只借用凭据，
+    /// Base64 编码推迟到发送时进行，因此凭据需在请求完成前保持有效。
+    /// 与 `with_bearer_token` 互斥，同时设置时请求返回 `InvalidConfig`。
+    pub const fn with_basic_auth(mut self, user: &'a str, pass: &'a str) -> Self {
+        self.basic_auth = Some((user, pass));
+        self
+    }
+
     /// 设置 DTU 串口数据类型。
     pub const fn with_data_type(mut self, data_type: HttpDataType) -> Self {
         self.data_type = data_type;
         self
     }
+
+    /// 设置追加到 URL 的查询参数。
+    ///
+    /// `HttpRequest` 只借用调用方数据（保持 `Copy` 与 `const fn` 构造），
+    /// 因此这里仅保存键值对，百分号编码与拼接推迟到发送时进行，
+    /// 代价是每次发送都会分配一个拼接后的 URL 字符串。
+    ///
+    /// 参数严格按传入顺序输出，不排序、不去重（重复的键依次输出）。
+    pub const fn with_encoded_query(mut self, pairs: &'a [(&'a str, &'a str)]) -> Self {
+        self.query = pairs;
+        self.sort_query = false;
+        self
+    }
+
+    /// 同 [`with_encoded_query`](Self::with_encoded_query)，但发送时按键（字节序）排序，
+    /// 用于要求规范化参数顺序的签名方案（如 HMAC 签名）。
+    ///
+    /// 排序是稳定的：同名键保持传入时的相对顺序。
+    pub const fn with_sorted_query(mut self, pairs: &'a [(&'a str, &'a str)]) -> Self {
+        self.query = pairs;
+        self.sort_query = true;
+        self
+    }
+
+    /// 以 `QUERY` 数据类型发送查询参数：同时设置 `data_type = Query`，
+    /// 发送时键值对百分号编码、以 `&` 连接后作为透传 payload（`body` 被忽略），
+    /// 由模块拼接到 URL 后。
+    ///
+    /// 与 [`with_encoded_query`](Self::with_encoded_query) 的区别：后者在本地把参数拼进
+    /// `AT+HTPURL` 下发的 URL，占用 URL 长度（受 `max_url_len` 限制）；
+    /// 本接口的参数随 payload 透传，适合参数较长或每次变化而 URL 固定的场景。
+    /// 参数同样按传入顺序输出，不排序、不去重。
+    pub const fn with_query_pairs(mut self, pairs: &'a [(&'a str, &'a str)]) -> Self {
+        self.query_payload = pairs;
+        self.data_type = HttpDataType::Query;
+        self
+    }
+
+    /// 为本次请求单独设置等待 HTTP 响应首字节的超时（覆盖配置值）。
+    pub const fn with_http_first_timeout(mut self, timeout: Duration) -> Self {
+        self.http_first_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置请求字节范围（`Range: bytes=<start>-<end>`），`end` 为 `None` 时读到末尾。
+    ///
+    /// 与 `with_bearer_token` 一样只保存参数，头部在发送时生成，不占用 `headers`。
+    pub const fn with_range(mut self, start: u64, end: Option<u64>) -> Self {
+        self.range = Some((start, end));
+        self
+    }
+
+    /// 设置 `If-None-Match` 头（通常取自上次响应的 [`HttpResponse::etag`]），
+    /// 资源未变化时服务器返回 304。
+    pub const fn with_if_none_match(mut self, tag: &'a str) -> Self {
+        self.if_none_match = Some(tag);
+        self
+    }
+}
+
+impl<'a> HttpRequest<'a> {
+    /// 实际透传的 payload：设置了 `query_payload` 时为编码后的查询串，否则为 `body`。
+    pub(crate) fn payload(&self) -> Cow<'a, [u8]> {
+        if self.query_payload.is_empty() {
+            return Cow::Borrowed(self.body);
+        }
+        let mut out = String::new();
+        encode_query_pairs(self.query_payload, &mut out);
+        Cow::Owned(out.into_bytes())
+    }
+
+    /// 拼接实际发送的 URL：去除 `#fragment`（仅客户端使用，不应发往服务器），
+    /// 再追加编码后的查询参数。
+    pub(crate) fn build_url(&self) -> String {
+        let base = self.url.split_once('#').map_or(self.url, |(base, _)| base);
+        if self.query.is_empty() {
+            return String::from(base);
+        }
+
+        let mut out = String::from(base);
+        if !base.contains('?') {
+            out.push('?');
+        } else if !base.ends_with('?') && !base.ends_with('&') {
+            out.push('&');
+        }
+        if self.sort_query {
+            let mut sorted = self.query.to_vec();
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            encode_query_pairs(&sorted, &mut out);
+        } else {
+            encode_query_pairs(self.query, &mut out);
+        }
+        out
+    }
+}
+
+/// HTTP 状态码类别（按百位划分）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusClass {
+    /// 1xx
+    Informational,
+    /// 2xx
+    Success,
+    /// 3xx
+    Redirect,
+    /// 4xx
+    ClientError,
+    /// 5xx
+    ServerError,
+}
+
+impl StatusClass {
+    /// 由状态码得到类别，不在 100~599 时返回 `None`。
+    pub fn from_status(code: u16) -> Option<Self> {
+        match code {
+            100..=199 => Some(Self::Informational),
+            200..=299 => Some(Self::Success),
+            300..=399 => Some(Self::Redirect),
+            400..=499 => Some(Self::ClientError),
+            500..=599 => Some(Self::ServerError),
+            _ => None,
+        }
+    }
 }
 
 /// HTTP 响应。
@@ -110,6 +454,8 @@ impl<'a> HttpRequest<'a> {
 pub struct HttpResponse {
     pub status_code: Option<u16>,
     pub raw: Vec<u8>,
+    /// 解析 URC 使用的标记，由客户端按 `DtuAtHttpConfig::markers` 填入。
+    pub markers: Markers,
 }
 
 impl HttpResponse {
@@ -118,189 +464,810 @@ impl HttpResponse {
         matches!(self.status_code, Some(200..=299))
     }
 
+    /// 是否为重定向响应（3xx）。
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status_code, Some(300..=399))
+    }
+
+    /// 是否为客户端错误（4xx）。
+    pub fn is_client_error(&self) -> bool {
+        matches!(self.status_code, Some(400..=499))
+    }
+
+    /// 是否为服务器错误（5xx）。
+    pub fn is_server_error(&self) -> bool {
+        matches!(self.status_code, Some(500..=599))
+    }
+
+    /// 状态码类别；`status_code` 为 `None` 或不在 100~599 时返回 `None`。
+    pub fn status_class(&self) -> Option<StatusClass> {
+        self.status_code.and_then(StatusClass::from_status)
+    }
+
+    /// 状态行 `HTTP/1.x NNN Reason` 中的原因短语（去除首尾空白），如 `"Not Found"`。
+    ///
+    /// 仅有 `FS@HTTP` URC（无状态行）或原因短语为空时返回 `None`。
+    pub fn status_reason(&self) -> Option<String> {
+        let idx = find_final_status_line(&self.raw)?;
+        let line = &self.raw[idx..];
+        let line_end = line
+            .iter()
+            .position(|b| *b == b'\r' || *b == b'\n')
+            .unwrap_or(line.len());
+        let line = core::str::from_utf8(&line[..line_end]).ok()?;
+        let mut parts = line.splitn(3, ' ');
+        let _version = parts.next()?;
+        let code = parts.next()?;
+        if code.is_empty() || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let reason = parts.next()?.trim();
+        (!reason.is_empty()).then(|| String::from(reason))
+    }
+
+    /// 是否为 304 Not Modified（`with_if_none_match` 命中，资源未变化）。
+    pub fn is_not_modified(&self) -> bool {
+        self.status_code == Some(304)
+    }
+
+    /// 是否为部分内容响应（206，对应 `with_range` 请求）。
+    pub fn is_partial_content(&self) -> bool {
+        self.status_code == Some(206)
+    }
+
     /// 将原始响应按 UTF-8 宽松解码为字符串。
     pub fn as_utf8_lossy(&self) -> String {
         String::from_utf8_lossy(&self.raw).into_owned()
     }
 
-    /// 尝试提取 HTTP body。
+    /// 尝试提取 HTTP body（按 [`markers`](Self::markers) 识别 URC 形式的 body）。
     pub fn http_body(&self) -> Option<&[u8]> {
-        let raw = self.raw.as_slice();
-
-        if let Some(http_idx) = find_subslice(raw, b"HTTP/1.") {
-            let http = &raw[http_idx..];
+        self.http_body_with(&self.markers)
+    }
 
-            if let Some((header_end, sep_len)) = find_header_boundary(http) {
-                let body_start = http_idx + header_end + sep_len;
-                let body = &raw[body_start..];
+    /// 同 [`http_body`](Self::http_body)，但改用给定的 `markers` 识别 URC 形式的 body。
+    pub fn http_body_with(&self, markers: &Markers) -> Option<&[u8]> {
+        extract_http_body(&self.raw, markers)
+    }
 
-                if body.starts_with(b"FS@") {
-                    return None;
-                }
+    /// 按 `Content-Type` 声明的 charset 将 body 解码为字符串（无 body 时为空串）。
+    ///
+    /// 声明 `ISO-8859-1` / `latin1` 时逐字节映射为 Unicode；其余（含未声明、未知 charset）
+    /// 按 UTF-8 宽松解码。
+    pub fn text(&self) -> String {
+        let body = self.http_body().unwrap_or(&[]);
+        let latin1 = self
+            .content_type()
+            .and_then(|(_, charset)| charset)
+            .is_some_and(|charset| {
+                ["iso-8859-1", "latin1", "latin-1"]
+                    .iter()
+                    .any(|name| charset.eq_ignore_ascii_case(name))
+            });
 
-                if let Some(content_len) = parse_content_length(&http[..header_end]) {
-                    if content_len == 0 {
-                        return Some(&[]);
-                    }
+        if latin1 {
+            body.iter().map(|&b| char::from(b)).collect()
+        } else {
+            String::from_utf8_lossy(body).into_owned()
+        }
+    }
 
-                    if body.len() >= content_len {
-                        return Some(&body[..content_len]);
-                    }
+    /// 返回解码后的 body。
+    ///
+    /// 响应声明 `Transfer-Encoding: chunked` 时按分块格式解码并拼接数据，
+    /// 遇到终止块 `0\r\n` 即停止（其后的 `FS@` URC 被忽略）；
+    /// 否则返回 [`http_body`](Self::http_body) 的拷贝。
+    pub fn decoded_body(&self) -> Option<Vec<u8>> {
+        let chunked = self.header_values("Transfer-Encoding").iter().any(|v| {
+            v.split(',')
+                .any(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+        });
+        if !chunked {
+            return self.http_body().map(<[u8]>::to_vec);
+        }
 
-                    return Some(body);
-                }
+        let raw = self.raw.as_slice();
+        let http_idx = find_final_status_line(raw)?;
+        let http = &raw[http_idx..];
+        let (header_end, sep_len) = find_header_boundary(http)?;
+        Some(decode_chunked(&http[header_end + sep_len..]))
+    }
 
-                if body.is_empty() {
-                    return None;
-                }
+    /// 按 `Content-Encoding` 解压 body（在 [`decoded_body`](Self::decoded_body) 基础上）。
+    ///
+    /// 启用 `decompress` feature 时支持 `gzip` 与 `deflate`（zlib 封装或裸 deflate）；
+    /// 未启用 feature、未声明或不识别的编码原样返回 body。
+    ///
+    /// `max_len` 限制返回 body 的长度（解压后），防止压缩炸弹耗尽堆内存：超出返回 `ResponseTooLarge`，
+    /// 缺少 body 返回 `BodyMissing`，压缩数据损坏返回 `BadResponse`。
+    pub fn decoded_body_with_encoding(&self, max_len: usize) -> Result<Vec<u8>, DtuAtError> {
+        let body = self.decoded_body().ok_or(DtuAtError::BodyMissing)?;
 
-                return Some(body);
-            }
-        }
+        #[cfg(feature = "decompress")]
+        if let Some(encoding) = self.header_value("Content-Encoding") {
+            use miniz_oxide::inflate::{
+                decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit,
+            };
 
-        if let Some(idx) = find_subslice(raw, b"\r\n\r\n") {
-            let body = &raw[idx + 4..];
-            if body.starts_with(b"FS@") {
-                return None;
+            let encoding = encoding.trim();
+            if encoding.eq_ignore_ascii_case("gzip") || encoding.eq_ignore_ascii_case("x-gzip") {
+                return inflate_gzip(&body, max_len);
             }
-            return Some(body);
-        }
-
-        if let Some(idx) = find_subslice(raw, b"\n\n") {
-            let body = &raw[idx + 2..];
-            if body.starts_with(b"FS@") {
-                return None;
+            if encoding.eq_ignore_ascii_case("deflate") {
+                return match decompress_to_vec_zlib_with_limit(&body, max_len) {
+                    Ok(out) => Ok(out),
+                    Err(e) if is_inflate_limit(&e) => Err(DtuAtError::ResponseTooLarge),
+                    Err(_) => decompress_to_vec_with_limit(&body, max_len).map_err(inflate_error),
+                };
             }
-            return Some(body);
         }
 
-        if let Some(body) = extract_urc_style_body(raw) {
-            return Some(body);
+        if body.len() > max_len {
+            return Err(DtuAtError::ResponseTooLarge);
         }
+        Ok(body)
+    }
 
-        None
+    /// 将 body 反序列化为 `V`（需 `serde` feature）。
+    ///
+    /// 基于 [`decoded_body`](Self::decoded_body)（兼容 chunked）；缺少 body 返回 `BodyMissing`，
+    /// JSON 不合法或与 `V` 不匹配返回 `Serde`。
+    #[cfg(feature = "serde")]
+    pub fn json<V: serde::de::DeserializeOwned>(&self) -> Result<V, DtuAtError> {
+        let body = self.decoded_body().ok_or(DtuAtError::BodyMissing)?;
+        // 转义字符串需要额外的反转义缓冲，长度不会超过 body 本身。
+        let mut unescape = alloc::vec![0u8; body.len()];
+        serde_json_core::from_slice_escaped::<V>(&body, &mut unescape)
+            .map(|(value, _)| value)
+            .map_err(|_| DtuAtError::Serde("json deserialize failed"))
     }
 
     /// 从 HTTP 头中解析声明的 `Content-Length`。
     pub fn declared_content_length(&self) -> Option<usize> {
-        let raw = self.raw.as_slice();
-        let http_idx = find_subslice(raw, b"HTTP/1.")?;
-        let http = &raw[http_idx..];
-        let (header_end, _) = find_header_boundary(http)?;
-        parse_content_length(&http[..header_end])
+        extract_content_length(&self.raw)
     }
-}
 
-fn find_header_boundary(http: &[u8]) -> Option<(usize, usize)> {
-    if let Some(idx) = find_subslice(http, b"\r\n\r\n") {
-        return Some((idx, 4));
-    }
-    if let Some(idx) = find_subslice(http, b"\n\n") {
-        return Some((idx, 2));
+    /// 收到的 body 长度是否与声明的 `Content-Length` 一致；未声明时返回 `None`。
+    ///
+    /// 基于 [`http_body`](Self::http_body)：超出声明长度的部分已被截去，
+    /// 因此主要用于发现 body 未收齐（截断）的情况。
+    pub fn body_matches_content_length(&self) -> Option<bool> {
+        let declared = self.declared_content_length()?;
+        Some(self.http_body().map_or(0, <[u8]>::len) == declared)
     }
-    None
-}
-
-fn extract_urc_style_body(raw: &[u8]) -> Option<&[u8]> {
-    for marker in [
-        b"FS@HTTP INFO CODE:".as_slice(),
-        b"FS@HTTP SUCCESS CODE:".as_slice(),
-        b"FS@HTTP REDIRECT CODE:".as_slice(),
-        b"FS@HTTP CLIENT ERROR CODE:".as_slice(),
-        b"FS@HTTP SERVER ERROR CODE:".as_slice(),
-    ] {
-        let Some(idx) = find_subslice(raw, marker) else {
-            continue;
-        };
-
-        let mut pos = idx + marker.len();
 
-        let Some(comma_rel) = raw[pos..].iter().position(|b| *b == b',') else {
-            continue;
+    /// 解析响应头列表（按出现顺序，名称与值均已去除首尾空白）。
+    ///
+    /// 折叠行（以空格 / Tab 开头）拼接到上一个头的值，以 `markers.urc_prefix` 开头的 URC 行被忽略；
+    /// 未找到 HTTP 状态行时返回空列表。
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let mut out: Vec<(String, String)> = Vec::new();
+        let Some(block) = header_block(&self.raw) else {
+            return out;
         };
-        pos += comma_rel + 1;
 
-        while pos < raw.len() && raw[pos].is_ascii_digit() {
-            pos += 1;
-        }
-        while pos < raw.len()
-            && (raw[pos] == b' ' || raw[pos] == b'\t' || raw[pos] == b'\r' || raw[pos] == b'\n')
-        {
-            pos += 1;
+        for line in header_lines(block) {
+            if line.starts_with(self.markers.urc_prefix) {
+                continue;
+            }
+            if matches!(line.first(), Some(b' ' | b'\t')) {
+                if let Some((_, value)) = out.last_mut() {
+                    let cont = trim_ascii_whitespace(line);
+                    if !cont.is_empty() {
+                        value.push(' ');
+                        value.push_str(&String::from_utf8_lossy(cont));
+                    }
+                }
+                continue;
+            }
+            if let Some((name, value)) = split_header_line(line) {
+                out.push((
+                    String::from_utf8_lossy(name).into_owned(),
+                    String::from_utf8_lossy(value).into_owned(),
+                ));
+            }
         }
 
-        if pos >= raw.len() {
-            continue;
-        }
+        out
+    }
 
-        let mut end = raw.len();
-        if let Some(rel) = find_subslice(&raw[pos..], b"\r\nFS@") {
-            end = pos + rel;
-        } else if let Some(rel) = find_subslice(&raw[pos..], b"\nFS@") {
-            end = pos + rel;
-        } else if pos + 1 < raw.len() {
-            if let Some(rel) = find_subslice(&raw[pos + 1..], b"FS@") {
-                end = pos + 1 + rel;
+    /// 按名称（区分大小写）查找第一个响应头的值。
+    ///
+    /// 零拷贝返回原始缓冲中的切片，因此折叠行的后续部分不包含在内；
+    /// 需要完整值时使用 [`headers`](Self::headers)。
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let block = header_block(&self.raw)?;
+        for line in header_lines(block) {
+            if line.starts_with(self.markers.urc_prefix)
+                || matches!(line.first(), Some(b' ' | b'\t'))
+            {
+                continue;
+            }
+            if let Some((n, value)) = split_header_line(line)
+                && n == name.as_bytes()
+            {
+                return core::str::from_utf8(value).ok();
             }
         }
+        None
+    }
 
-        let body = trim_ascii_whitespace(&raw[pos..end]);
-        if body.is_empty() || body.starts_with(b"FS@") {
-            continue;
-        }
-        return Some(body);
+    /// 按名称（ASCII 大小写不敏感）查找第一个响应头的值。
+    pub fn header_value(&self, name: &str) -> Option<String> {
+        self.headers()
+            .into_iter()
+            .find(|(n, _)| eq_ascii_case(n.as_bytes(), name.as_bytes()))
+            .map(|(_, v)| v)
     }
 
-    None
-}
+    /// 重定向目标：`Location` 头（大小写不敏感）的值。
+    ///
+    /// 只在原始响应包含 HTTP 头块时可用；仅有 `FS@HTTP` URC（未开启响应头过滤位）时返回 `None`。
+    pub fn location(&self) -> Option<String> {
+        self.header_value("Location").filter(|v| !v.is_empty())
+    }
 
-fn trim_ascii_whitespace(data: &[u8]) -> &[u8] {
-    let mut start = 0usize;
-    let mut end = data.len();
+    /// `ETag` 头（大小写不敏感）的值，保留引号与 `W/` 前缀，可原样传给 `with_if_none_match`。
+    pub fn etag(&self) -> Option<String> {
+        self.header_value("ETag").filter(|v| !v.is_empty())
+    }
 
-    while start < end && data[start].is_ascii_whitespace() {
-        start += 1;
+    /// 解析 `Content-Range: bytes <start>-<end>/<total>`，返回 `(start, end, total)`。
+    ///
+    /// `total` 为 `*`（长度未知）时为 `None`；缺少该头或格式不符时返回 `None`。
+    pub fn content_range(&self) -> Option<(u64, u64, Option<u64>)> {
+        let value = self.header_value("Content-Range")?;
+        let (unit, rest) = value.trim().split_once(' ')?;
+        if !unit.eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+        let (span, total) = rest.trim().split_once('/')?;
+        let (start, end) = span.split_once('-')?;
+        let total = match total.trim() {
+            "*" => None,
+            t => Some(t.parse().ok()?),
+        };
+        Some((start.trim().parse().ok()?, end.trim().parse().ok()?, total))
     }
-    while end > start && data[end - 1].is_ascii_whitespace() {
-        end -= 1;
+
+    /// 提取全部 `Set-Cookie` 头开头的 `name=value`（忽略 `Path`、`HttpOnly` 等属性）。
+    ///
+    /// 基于 [`headers`](Self::headers)，因此折叠行与多行 `Set-Cookie` 均可处理；
+    /// 不含 `=` 或名称为空的条目被跳过。
+    pub fn cookies(&self) -> Vec<(String, String)> {
+        self.header_values("Set-Cookie")
+            .iter()
+            .filter_map(|value| {
+                let pair = value.split(';').next()?;
+                let (name, value) = pair.split_once('=')?;
+                let name = name.trim();
+                (!name.is_empty()).then(|| (String::from(name), String::from(value.trim())))
+            })
+            .collect()
     }
 
-    &data[start..end]
-}
+    /// 解析 `Content-Type` 头，返回 `(媒体类型, charset)`。
+    ///
+    /// 媒体类型去除参数与首尾空白；`charset=` 参数名大小写不敏感，值会去掉引号。
+    /// 缺少 `Content-Type` 头时返回 `None`。
+    pub fn content_type(&self) -> Option<(String, Option<String>)> {
+        let value = self.header_value("Content-Type")?;
+        let mut parts = value.split(';');
+        let media_type = String::from(parts.next().unwrap_or("").trim());
+        let charset = parts.find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| String::from(value.trim().trim_matches('"')))
+        });
+        Some((media_type, charset))
+    }
 
-fn parse_content_length(header: &[u8]) -> Option<usize> {
-    let mut i = 0usize;
-    while i < header.len() {
-        let line_end = header[i..]
-            .iter()
-            .position(|b| *b == b'\n')
-            .map(|p| i + p)
-            .unwrap_or(header.len());
+    /// 按名称（ASCII 大小写不敏感）返回全部同名响应头的值，如多个 `Set-Cookie`。
+    pub fn header_values(&self, name: &str) -> Vec<String> {
+        self.headers()
+            .into_iter()
+            .filter(|(n, _)| eq_ascii_case(n.as_bytes(), name.as_bytes()))
+            .map(|(_, v)| v)
+            .collect()
+    }
+}
 
-        let line = &header[i..line_end];
-        if line.len() >= 15 {
-            let prefix = b"Content-Length:";
-            if line.len() >= prefix.len() && eq_ascii_case_prefix(line, prefix) {
-                let mut j = prefix.len();
-                while j < line.len() && (line[j] == b' ' || line[j] == b'\t') {
-                    j += 1;
-                }
-                return parse_usize_from_prefix(&line[j..]);
+/// 格式：`HTTP <code> (<len> bytes): <前 160 字节的 UTF-8 宽松预览>`，状态码缺失时显示 `???`。
+impl core::fmt::Display for HttpResponse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.status_code {
+            Some(code) => write!(f, "HTTP {} ({} bytes): ", code, self.raw.len())?,
+            None => write!(f, "HTTP ??? ({} bytes): ", self.raw.len())?,
+        }
+        for chunk in response_preview(&self.raw).utf8_chunks() {
+            f.write_str(chunk.valid())?;
+            if !chunk.invalid().is_empty() {
+                f.write_str("\u{FFFD}")?;
             }
         }
-
-        i = if line_end < header.len() {
-            line_end + 1
-        } else {
-            header.len()
-        };
+        Ok(())
     }
-    None
 }
 
-fn parse_usize_from_prefix(data: &[u8]) -> Option<usize> {
-    let mut started = false;
-    let mut value: usize = 0;
+#[cfg(feature = "dtu-log-defmt")]
+impl defmt::Format for HttpResponse {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self.status_code {
+            Some(code) => defmt::write!(f, "HTTP {} ({} bytes): ", code, self.raw.len()),
+            None => defmt::write!(f, "HTTP ??? ({} bytes): ", self.raw.len()),
+        }
+        for chunk in response_preview(&self.raw).utf8_chunks() {
+            defmt::write!(f, "{=str}", chunk.valid());
+            if !chunk.invalid().is_empty() {
+                defmt::write!(f, "\u{FFFD}");
+            }
+        }
+    }
+}
+
+/// 请求分阶段耗时（毫秒，由 `request_timed` 返回）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestTiming {
+    /// 进入命令模式（`AT` 探测 / `+++` / 等待重启）。
+    pub enter_command_mode_ms: u32,
+    /// 下发通道参数（`WKMOD` ~ `HTPDT`）。
+    pub config_apply_ms: u32,
+    /// `AT+S` 保存并等待 `FS@HTTP OK`，含静置时间。
+    pub save_wait_ms: u32,
+    /// payload 发出到收到响应首字节。
+    pub first_byte_ms: u32,
+    /// 整个调用（含重试与重定向）。
+    pub total_ms: u32,
+}
+
+impl RequestTiming {
+    pub(crate) const ZERO: Self = Self {
+        enter_command_mode_ms: 0,
+        config_apply_ms: 0,
+        save_wait_ms: 0,
+        first_byte_ms: 0,
+        total_ms: 0,
+    };
+}
+
+/// 流式下载结果（由 `request_to_writer` 返回）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamedResponse {
+    pub status_code: Option<u16>,
+    /// 写入 sink 的 body 字节数。
+    pub total_len: usize,
+}
+
+impl StreamedResponse {
+    /// 是否为成功响应。
+    pub fn is_success(&self) -> bool {
+        matches!(self.status_code, Some(200..=299))
+    }
+}
+
+/// 借用调用方缓冲的 HTTP 响应（由 `request_into` 返回，无堆分配）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpResponseRef<'a> {
+    pub status_code: Option<u16>,
+    pub raw: &'a [u8],
+    /// 解析 URC 使用的标记，由客户端按 `DtuAtHttpConfig::markers` 填入。
+    pub markers: Markers,
+}
+
+impl<'a> HttpResponseRef<'a> {
+    /// 是否为成功响应。
+    pub fn is_success(&self) -> bool {
+        matches!(self.status_code, Some(200..=299))
+    }
+
+    /// 尝试提取 HTTP body（借用原缓冲，按 [`markers`](Self::markers) 识别 URC）。
+    pub fn http_body(&self) -> Option<&'a [u8]> {
+        self.http_body_with(&self.markers)
+    }
+
+    /// 同 [`http_body`](Self::http_body)，但改用给定的 `markers` 识别 URC 形式的 body。
+    pub fn http_body_with(&self, markers: &Markers) -> Option<&'a [u8]> {
+        extract_http_body(self.raw, markers)
+    }
+
+    /// 从 HTTP 头中解析声明的 `Content-Length`。
+    pub fn declared_content_length(&self) -> Option<usize> {
+        extract_content_length(self.raw)
+    }
+
+    /// 拷贝为拥有所有权的 [`HttpResponse`]。
+    pub fn to_owned_response(&self) -> HttpResponse {
+        HttpResponse {
+            status_code: self.status_code,
+            raw: self.raw.to_vec(),
+            markers: self.markers,
+        }
+    }
+}
+
+/// 固定容量 `N` 字节的 HTTP 响应（由 `request_heapless` 返回，需 `heapless` feature）。
+///
+/// 响应缓冲为 `heapless::Vec<u8, N>`，运行期不增长；解析方法与 [`HttpResponseRef`] 一致，
+/// 其余方法可经 [`as_ref`](Self::as_ref) 取得借用视图后调用。
+#[cfg(feature = "heapless")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponseN<const N: usize> {
+    pub status_code: Option<u16>,
+    pub raw: heapless::Vec<u8, N>,
+    /// 解析 URC 使用的标记，由客户端按 `DtuAtHttpConfig::markers` 填入。
+    pub markers: Markers,
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> HttpResponseN<N> {
+    /// 是否为成功响应。
+    pub fn is_success(&self) -> bool {
+        matches!(self.status_code, Some(200..=299))
+    }
+
+    /// 尝试提取 HTTP body（借用内部缓冲，按 [`markers`](Self::markers) 识别 URC）。
+    pub fn http_body(&self) -> Option<&[u8]> {
+        self.http_body_with(&self.markers)
+    }
+
+    /// 同 [`http_body`](Self::http_body)，但改用给定的 `markers` 识别 URC 形式的 body。
+    pub fn http_body_with(&self, markers: &Markers) -> Option<&[u8]> {
+        extract_http_body(&self.raw, markers)
+    }
+
+    /// 从 HTTP 头中解析声明的 `Content-Length`。
+    pub fn declared_content_length(&self) -> Option<usize> {
+        extract_content_length(&self.raw)
+    }
+
+    /// 借用为 [`HttpResponseRef`]。
+    pub fn as_ref(&self) -> HttpResponseRef<'_> {
+        HttpResponseRef {
+            status_code: self.status_code,
+            raw: &self.raw,
+            markers: self.markers,
+        }
+    }
+}
+
+/// 从原始响应中提取 HTTP body（`HttpResponse` / `HttpResponseRef` 共用）。
+fn extract_http_body<'a>(raw: &'a [u8], markers: &Markers) -> Option<&'a [u8]> {
+    if let Some(http_idx) = find_final_status_line(raw) {
+        let http = &raw[http_idx..];
+
+        if let Some((header_end, sep_len)) = find_header_boundary(http) {
+            let body_start = http_idx + header_end + sep_len;
+            let body = &raw[body_start..];
+
+            if body.starts_with(markers.urc_prefix) {
+                return None;
+            }
+
+            if let Some(content_len) = parse_content_length(&http[..header_end]) {
+                if content_len == 0 {
+                    return Some(&[]);
+                }
+
+                if body.len() >= content_len {
+                    return Some(&body[..content_len]);
+                }
+
+                return Some(body);
+            }
+
+            if body.is_empty() {
+                return None;
+            }
+
+            return Some(body);
+        }
+    }
+
+    if let Some(idx) = find_subslice(raw, b"\r\n\r\n") {
+        let body = &raw[idx + 4..];
+        if body.starts_with(markers.urc_prefix) {
+            return None;
+        }
+        return Some(body);
+    }
+
+    if let Some(idx) = find_subslice(raw, b"\n\n") {
+        let body = &raw[idx + 2..];
+        if body.starts_with(markers.urc_prefix) {
+            return None;
+        }
+        return Some(body);
+    }
+
+    if let Some(body) = extract_urc_style_body(raw, markers) {
+        return Some(body);
+    }
+
+    None
+}
+
+fn extract_content_length(raw: &[u8]) -> Option<usize> {
+    let http_idx = find_final_status_line(raw)?;
+    let http = &raw[http_idx..];
+    let (header_end, _) = find_header_boundary(http)?;
+    parse_content_length(&http[..header_end])
+}
+
+/// 按声明的 `Content-Length` 计算响应结束位置（`raw` 内的偏移）：头块已完整且长度可解析时返回。
+///
+/// 存在 `Transfer-Encoding`（如 chunked）时长度以分块为准，返回 `None`。
+pub(crate) fn content_length_end(raw: &[u8]) -> Option<usize> {
+    let (body_start, content_len) = body_bounds(raw)?;
+    Some(body_start + content_len?)
+}
+
+/// 头块已完整时返回 body 起始偏移与声明的 `Content-Length`。
+///
+/// 存在 `Transfer-Encoding` 或未声明长度时长度为 `None`。
+pub(crate) fn body_bounds(raw: &[u8]) -> Option<(usize, Option<usize>)> {
+    let http_idx = find_final_status_line(raw)?;
+    let http = &raw[http_idx..];
+    let (header_end, sep_len) = find_header_boundary(http)?;
+    let head = &http[..header_end];
+    let has_transfer_encoding = header_lines(head)
+        .filter_map(split_header_line)
+        .any(|(name, _)| name.eq_ignore_ascii_case(b"Transfer-Encoding"));
+    let content_len = if has_transfer_encoding {
+        None
+    } else {
+        parse_content_length(head)
+    };
+    Some((http_idx + header_end + sep_len, content_len))
+}
+
+/// 日志 / 格式化输出使用的响应预览长度（字节）。
+pub(crate) const RESPONSE_PREVIEW_LEN: usize = 160;
+
+/// 截取响应的前 [`RESPONSE_PREVIEW_LEN`] 字节用于预览。
+pub(crate) fn response_preview(raw: &[u8]) -> &[u8] {
+    &raw[..core::cmp::min(RESPONSE_PREVIEW_LEN, raw.len())]
+}
+
+/// 响应头区域：状态行之后、空行之前（不含状态行本身）。
+fn header_block(raw: &[u8]) -> Option<&[u8]> {
+    let http_idx = find_final_status_line(raw)?;
+    let http = &raw[http_idx..];
+    let (header_end, _) = find_header_boundary(http)?;
+    let head = &http[..header_end];
+    let status_end = head
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|p| p + 1)
+        .unwrap_or(head.len());
+    Some(&head[status_end..])
+}
+
+/// 按 LF 切分头区域，去掉行尾 CR 并跳过空行。
+fn header_lines(block: &[u8]) -> impl Iterator<Item = &[u8]> {
+    block
+        .split(|b| *b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+}
+
+/// 在第一个 `:` 处切分 `Name: value`，返回去除空白后的名称与值。
+fn split_header_line(line: &[u8]) -> Option<(&[u8], &[u8])> {
+    let colon = line.iter().position(|b| *b == b':')?;
+    let name = trim_ascii_whitespace(&line[..colon]);
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, trim_ascii_whitespace(&line[colon + 1..])))
+}
+
+fn find_header_boundary(http: &[u8]) -> Option<(usize, usize)> {
+    if let Some(idx) = find_subslice(http, b"\r\n\r\n") {
+        return Some((idx, 4));
+    }
+    if let Some(idx) = find_subslice(http, b"\n\n") {
+        return Some((idx, 2));
+    }
+    None
+}
+
+/// 解码 chunked 分块数据；分块不完整时返回已解码部分。
+fn decode_chunked(mut data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    while let Some(line_end) = data.iter().position(|b| *b == b'\n') {
+        let line = &data[..line_end];
+        // 忽略 chunk extension（`;` 之后的内容）
+        let size_field = line.split(|b| *b == b';').next().unwrap_or(line);
+        let Some(size) = parse_hex_usize(trim_ascii_whitespace(size_field)) else {
+            break;
+        };
+        data = &data[line_end + 1..];
+        if size == 0 {
+            break;
+        }
+
+        let take = size.min(data.len());
+        out.extend_from_slice(&data[..take]);
+        if take < size {
+            break;
+        }
+        data = &data[take..];
+        data = data
+            .strip_prefix(b"\r\n")
+            .or_else(|| data.strip_prefix(b"\n"))
+            .unwrap_or(data);
+    }
+
+    out
+}
+
+/// 解压输出超过上限（而非数据损坏）。
+#[cfg(feature = "decompress")]
+fn is_inflate_limit(e: &miniz_oxide::inflate::DecompressError) -> bool {
+    e.status == miniz_oxide::inflate::TINFLStatus::HasMoreOutput
+}
+
+#[cfg(feature = "decompress")]
+fn inflate_error(e: miniz_oxide::inflate::DecompressError) -> DtuAtError {
+    if is_inflate_limit(&e) {
+        DtuAtError::ResponseTooLarge
+    } else {
+        DtuAtError::BadResponse
+    }
+}
+
+/// 解析 gzip 头（RFC 1952）后解压其中的 deflate 数据，输出不超过 `max_len` 字节。
+#[cfg(feature = "decompress")]
+fn inflate_gzip(data: &[u8], max_len: usize) -> Result<Vec<u8>, DtuAtError> {
+    let deflate = gzip_payload(data).ok_or(DtuAtError::BadResponse)?;
+    miniz_oxide::inflate::decompress_to_vec_with_limit(deflate, max_len).map_err(inflate_error)
+}
+
+/// gzip 头之后、CRC32 + ISIZE 尾部之前的 deflate 数据。
+#[cfg(feature = "decompress")]
+fn gzip_payload(data: &[u8]) -> Option<&[u8]> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if data.len() < 18 || data[0] != 0x1F || data[1] != 0x8B || data[2] != 8 {
+        return None;
+    }
+
+    let flags = data[3];
+    let mut pos = 10usize;
+    if flags & FEXTRA != 0 {
+        let xlen = u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        pos += 2 + xlen;
+    }
+    if flags & FNAME != 0 {
+        pos += data.get(pos..)?.iter().position(|b| *b == 0)? + 1;
+    }
+    if flags & FCOMMENT != 0 {
+        pos += data.get(pos..)?.iter().position(|b| *b == 0)? + 1;
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    // 尾部 8 字节为 CRC32 + ISIZE
+    data.get(pos..data.len() - 8)
+}
+
+fn parse_hex_usize(data: &[u8]) -> Option<usize> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut value: usize = 0;
+    for &b in data {
+        let digit = (b as char).to_digit(16)? as usize;
+        value = value.checked_mul(16)?.checked_add(digit)?;
+    }
+    Some(value)
+}
+
+fn extract_urc_style_body<'a>(raw: &'a [u8], markers: &Markers) -> Option<&'a [u8]> {
+    let prefix = markers.urc_prefix;
+    for &marker in markers.code_prefixes {
+        let Some(idx) = find_subslice(raw, marker) else {
+            continue;
+        };
+
+        let mut pos = idx + marker.len();
+
+        let Some(comma_rel) = raw[pos..].iter().position(|b| *b == b',') else {
+            continue;
+        };
+        pos += comma_rel + 1;
+
+        while pos < raw.len() && raw[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        while pos < raw.len()
+            && (raw[pos] == b' ' || raw[pos] == b'\t' || raw[pos] == b'\r' || raw[pos] == b'\n')
+        {
+            pos += 1;
+        }
+
+        if pos >= raw.len() {
+            continue;
+        }
+
+        let mut end = raw.len();
+        if let Some(rel) = find_line_prefix(&raw[pos..], prefix) {
+            end = pos + rel;
+        } else if pos + 1 < raw.len() {
+            if let Some(rel) = find_subslice(&raw[pos + 1..], prefix) {
+                end = pos + 1 + rel;
+            }
+        }
+
+        let body = trim_ascii_whitespace(&raw[pos..end]);
+        if body.is_empty() || body.starts_with(prefix) {
+            continue;
+        }
+        return Some(body);
+    }
+
+    None
+}
+
+/// `data` 中第一个以 `prefix` 开头的行之前的换行符位置。
+fn find_line_prefix(data: &[u8], prefix: &[u8]) -> Option<usize> {
+    data.iter()
+        .enumerate()
+        .position(|(idx, b)| *b == b'\n' && data[idx + 1..].starts_with(prefix))
+}
+
+fn trim_ascii_whitespace(data: &[u8]) -> &[u8] {
+    let mut start = 0usize;
+    let mut end = data.len();
+
+    while start < end && data[start].is_ascii_whitespace() {
+        start += 1;
+    }
+    while end > start && data[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+
+    &data[start..end]
+}
+
+fn parse_content_length(header: &[u8]) -> Option<usize> {
+    let mut i = 0usize;
+    while i < header.len() {
+        let line_end = header[i..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|p| i + p)
+            .unwrap_or(header.len());
+
+        let line = &header[i..line_end];
+        if line.len() >= 15 {
+            let prefix = b"Content-Length:";
+            if line.len() >= prefix.len() && eq_ascii_case_prefix(line, prefix) {
+                let mut j = prefix.len();
+                while j < line.len() && (line[j] == b' ' || line[j] == b'\t') {
+                    j += 1;
+                }
+                return parse_usize_from_prefix(&line[j..]);
+            }
+        }
+
+        i = if line_end < header.len() {
+            line_end + 1
+        } else {
+            header.len()
+        };
+    }
+    None
+}
+
+fn parse_usize_from_prefix(data: &[u8]) -> Option<usize> {
+    let mut started = false;
+    let mut value: usize = 0;
     for &b in data {
         if b.is_ascii_digit() {
             started = true;
@@ -309,119 +1276,714 @@ fn parse_usize_from_prefix(data: &[u8]) -> Option<usize> {
             return Some(value);
         }
     }
-    if started { Some(value) } else { None }
-}
+    if started { Some(value) } else { None }
+}
+
+fn eq_ascii_case(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && eq_ascii_case_prefix(a, b)
+}
+
+fn eq_ascii_case_prefix(line: &[u8], prefix: &[u8]) -> bool {
+    if line.len() < prefix.len() {
+        return false;
+    }
+    for (a, b) in line[..prefix.len()].iter().zip(prefix.iter()) {
+        if a.to_ascii_lowercase() != b.to_ascii_lowercase() {
+            return false;
+        }
+    }
+    true
+}
+
+/// 请求级重试策略，决定哪些错误在 `max_request_attempts` 内重试。
+#[derive(Debug, Clone, Copy, Default)]
+pub enum RetryPolicy {
+    /// 仅可恢复错误：`Timeout` / `ModemTimeout` / `AtRejected`（含 `CmeError` / `CmsError`）/ `BadResponse`（默认）。
+    #[default]
+    TransientOnly,
+    /// 在 `TransientOnly` 基础上，`HttpFail(_)` 也重试。
+    IncludingHttpFail,
+    /// 从不重试。
+    None,
+    /// 自定义判定，如只重试 `HttpFail(3)`（TLS 握手）而不重试 `HttpFail(1)`（DNS）。
+    Custom(fn(&DtuAtError) -> bool),
+}
+
+// `Custom` 按函数地址比较（同一函数在不同代码单元中地址可能不同，仅作尽力比较）。
+impl PartialEq for RetryPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => core::ptr::fn_addr_eq(*a, *b),
+            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for RetryPolicy {}
+
+impl RetryPolicy {
+    /// 该错误是否应当重试。
+    pub fn should_retry(&self, e: &DtuAtError) -> bool {
+        let transient = matches!(
+            e,
+            DtuAtError::Timeout
+                | DtuAtError::ModemTimeout
+                | DtuAtError::AtRejected
+                | DtuAtError::CmeError(_)
+                | DtuAtError::CmsError(_)
+                | DtuAtError::BadResponse
+        );
+        match self {
+            Self::TransientOnly => transient,
+            Self::IncludingHttpFail => transient || matches!(e, DtuAtError::HttpFail(_)),
+            Self::None => false,
+            Self::Custom(f) => f(e),
+        }
+    }
+}
+
+/// [`DtuAtHttpConfig::terminal_markers`] 的默认值：HTTP 结果 URC（2xx~5xx、`FAIL` 与 `TIMEOUT`）。
+pub const DEFAULT_TERMINAL_MARKERS: &[&[u8]] = &[
+    b"FS@HTTP SUCCESS CODE:",
+    b"FS@HTTP REDIRECT CODE:",
+    b"FS@HTTP CLIENT ERROR CODE:",
+    b"FS@HTTP SERVER ERROR CODE:",
+    b"FS@HTTP FAIL:",
+    b"FS@HTTP TIMEOUT",
+];
+
+/// [`DtuAtHttpConfig::ok_tokens`] 的默认值。
+pub const DEFAULT_OK_TOKENS: &[&[u8]] = &[b"OK"];
+
+/// [`DtuAtHttpConfig::error_tokens`] 的默认值。
+pub const DEFAULT_ERROR_TOKENS: &[&[u8]] = &[b"ERR:", b"ERROR"];
+
+/// DTU 固件 HTTP URC 标记集合（[`DtuAtHttpConfig::markers`]），改版固件可在此整体替换。
+///
+/// 只影响 URC 的识别与解析；提前结束读取的终止标记由 `terminal_markers` 单独配置，
+/// 替换标记时通常需要同步修改。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Markers {
+    /// 所有 URC 行的公共前缀，用于把 URC 与 HTTP 数据区分开（不能为空）。
+    pub urc_prefix: &'static [u8],
+    /// 命令阶段视同 `OK` 的 URC。
+    pub http_ok: &'static [u8],
+    /// 固件级 HTTP 失败 URC，后随错误码。
+    pub http_fail: &'static [u8],
+    /// 模块侧 HTTP 超时 URC。
+    pub http_timeout: &'static [u8],
+    /// 携带状态码的结果 URC 前缀，后随 `<状态码>,<长度>`。
+    pub code_prefixes: &'static [&'static [u8]],
+    /// `AT+S` 后模块就绪、等待 payload 的 URC。
+    pub ready: &'static [u8],
+}
+
+impl Markers {
+    /// 标准固件的 `FS@HTTP ...` 标记。
+    pub const DEFAULT: Self = Self {
+        urc_prefix: b"FS@",
+        http_ok: b"FS@HTTP OK:",
+        http_fail: b"FS@HTTP FAIL:",
+        http_timeout: b"FS@HTTP TIMEOUT",
+        code_prefixes: &[
+            b"FS@HTTP INFO CODE:",
+            b"FS@HTTP SUCCESS CODE:",
+            b"FS@HTTP REDIRECT CODE:",
+            b"FS@HTTP CLIENT ERROR CODE:",
+            b"FS@HTTP SERVER ERROR CODE:",
+        ],
+        ready: b"FS@HTTP OK:",
+    };
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// DTU HTTP 客户端配置。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtuAtHttpConfig {
+    // ── 业务参数 ──────────────────────────────────────────────────────────────
+    /// DTU 通道号（1~4），对应 AT+WKMOD{N}、AT+HTPURL{N} 等指令的编号。
+    pub channel: u8,
+    /// DTU 固件 HTTP 请求超时（秒），写入 AT+HTPTIM 指令。
+    pub request_timeout_secs: u16,
+    /// AT+HTPPK 响应过滤掩码（0x03 = 返回头+体）。
+    pub response_filter_mask: u8,
+    /// 成功响应（2xx）时要求必须有 body；204/206/304 豁免。
+    pub require_body_on_success: bool,
+    /// 声明了 `Content-Length` 但 body 未收齐时继续读取（受 `http_followup_timeout` 约束），
+    /// 仍不足时返回 `BodyTruncated`；以延迟换取完整性，适合不稳定链路。
+    pub wait_for_full_body: bool,
+
+    // ── AT 命令时序 ───────────────────────────────────────────────────────────
+    /// `+++` 前的静默时间（Hayes 规范要求 ≥1s），建议 ≥1200ms。
+    pub cmd_guard_time: Duration,
+    /// 进入命令模式的转义序列，需与模块配置一致（默认 `+++`）。
+    pub command_mode_sequence: &'static [u8],
+    /// AT 命令等待第一个响应字节的超时。
+    pub at_first_timeout: Duration,
+    /// AT 命令收到首字节后的空闲超时（停止收集响应）。
+    pub at_idle_timeout: Duration,
+    /// AT 命令行结束符，默认 `\r\n`；少数固件要求单独的 `\r` 或 `\n`。
+    pub line_terminator: &'static [u8],
+    /// 模块开启回显（`ATE1`）时，解析前去除响应开头回显的命令行。
+    pub strip_command_echo: bool,
+    /// AT 命令成功令牌：独占一行或位于响应末行行尾时视为 `OK`（如 `CMD OK`）。
+    pub ok_tokens: &'static [&'static [u8]],
+    /// AT 命令失败令牌：响应中出现任一即视为拒绝（如 `+CME ERROR`）。
+    pub error_tokens: &'static [&'static [u8]],
+
+    // ── HTTP 响应接收时序 ──────────────────────────────────────────────────────
+    /// 等待 HTTP 响应第一字节的超时（网络 RTT 较长时应增大）。
+    pub http_first_timeout: Duration,
+    /// HTTP 响应空闲超时（用于判断本次数据接收结束）。
+    pub http_idle_timeout: Duration,
+    /// `collect_followup` 轮询等待首字节的超时。
+    pub http_followup_first_timeout: Duration,
+    /// `collect_followup` 总时限。
+    pub http_followup_timeout: Duration,
+    /// 等待 `FS@HTTP OK:` 就绪信号的总时限。
+    pub http_ready_timeout: Duration,
+    /// 终止标记：已接收数据以包含其一的完整行结尾时立即停止读取，不再等待空闲超时。
+    ///
+    /// 默认为 [`DEFAULT_TERMINAL_MARKERS`]；设为 `&[]` 时只依赖空闲超时。
+    pub terminal_markers: &'static [&'static [u8]],
+    /// 固件 HTTP URC 标记，默认为 [`Markers::DEFAULT`]。
+    pub markers: Markers,
+
+    // ── 命令模式进入与恢复 ─────────────────────────────────────────────────────
+    /// `enter_command_mode` 的总超时：覆盖从首次尝试到 DTU 重启恢复的全程。
+    /// 应 ≥ DTU AT+S 重启时间，建议 60s。
+    pub enter_cmd_timeout: Duration,
+    /// `wait_for_command_mode` 循环中每次 AT 探测的间隔。
+    pub enter_cmd_poll: Duration,
+
+    // ── 请求级重试 ────────────────────────────────────────────────────────────
+    /// 单次请求失败后的最大重试总次数（含首次），≥1。
+    /// 用于 ESP32 重启但 DTU 未重启等导致 AT 命令偶发失败的场景。
+    pub max_request_attempts: u8,
+    /// 重试退避基数：第 n 次重试前等待 `base * 2^(n-1)`；为 0 时不等待。
+    pub retry_backoff_base: Duration,
+    /// 重试退避上限。
+    pub retry_backoff_max: Duration,
+    /// 请求级重试策略。
+    pub retry_on: RetryPolicy,
+    /// 整个 `request()` 调用的总预算（含重试与重定向），优先于各阶段超时；`None` 表示不限制。
+    pub total_request_timeout: Option<Duration>,
+
+    // ── 可选功能 ──────────────────────────────────────────────────────────────
+    /// 发送前开启 DTU 固件调试 URC（AT+DEBUG=ON）。
+    pub enable_modem_debug_urc: bool,
+    /// 调试 URC 的行前缀：开启 `enable_modem_debug_urc` 时，以此开头的行从响应 `raw` 中移除（写入调试日志）。
+    pub debug_urc_prefix: &'static [u8],
+    /// 发送前查询并打印链路状态（AT+CREG? / AT+CSQ / AT+RUNST?）。
+    pub query_link_status_before_send: bool,
+    /// 配合 `query_link_status_before_send`：测得信号低于该阈值（dBm）时放弃发送并返回 `WeakSignal`；
+    /// `None` 表示只打印不拦截。
+    pub min_signal_dbm: Option<i16>,
+    /// HTTP 响应超时后重发一次 payload 再重试。
+    pub retry_payload_on_http_timeout: bool,
+    /// AT+S 后、进入透传模式前的额外等待时间。
+    pub post_entm_settle_time: Duration,
+    /// 单次请求允许的最大响应长度（字节）；`request()` 的响应缓冲按需增长到该长度，不预先分配。
+    pub max_response_len: usize,
+    /// HTTP 响应 / 就绪等待缓冲的初始容量（字节，不超过 `max_response_len`），减少读取过程中的扩容拷贝；
+    /// AT 命令响应从空缓冲按需增长，不使用该值。
+    pub response_capacity_hint: usize,
+    /// `AT+HTPURL` 允许的最大 URL 长度（含查询参数），超出时固件会静默截断。
+    pub max_url_len: usize,
+    /// 不限制 URL scheme（默认只接受 `http` / `https`），用于通过其他 scheme 隧道传输的 OEM 固件。
+    pub allow_any_scheme: bool,
+    /// 拒绝明文 `http://` URL（返回 `TlsRequired`）；与 `upgrade_insecure` 同时开启时先改写，不再拒绝。
+    pub require_tls: bool,
+    /// 发送前把 `http://` 改写为 `https://`（含重定向目标）；显式的 `:80` 一并去掉，其他端口不变。
+    pub upgrade_insecure: bool,
+    /// 每个请求都携带的默认头（如 `User-Agent`），排在请求头之前。
+    ///
+    /// 同名（大小写不敏感）请求头覆盖默认头，值为空的同名请求头移除该默认头；
+    /// 合并后的头部仍受 `AT+HTPHD` 256 字节限制（见 `split_long_headers`）。
+    pub default_headers: &'static [HttpHeader<'static>],
+    /// 未显式设置 `Host` 时按 URL 的 authority（去除 `userinfo@`，保留端口）自动追加该头。
+    pub auto_host_header: bool,
+    /// 请求体非空且未显式设置 `Content-Length` 时自动追加该头（`HttpDataType::Query` 除外）。
+    pub auto_content_length: bool,
+    /// 头部超过 `AT+HTPHD` 256 字节限制时，按头部边界拆成多条 `AT+HTPHD` 依次发送。
+    ///
+    /// 依赖固件支持追加头部；固件拒绝第二条时返回 `InvalidConfig`。
+    pub split_long_headers: bool,
+    /// `request()` 是否自动跟随 3xx 重定向（每一跳都是一次完整请求）。
+    pub follow_redirects: bool,
+    /// 自动跟随重定向的最大跳数。
+    pub max_redirects: u8,
+    /// AT 响应与 HTTP 响应读取的单次分块大小（字节），小于 32 时按 32 处理。
+    ///
+    /// AT 响应读入客户端持有的分块缓冲（首次读取时分配，之后复用），HTTP 响应按该粒度扩容并读入响应缓冲：
+    /// 越大则 `read` / 超时轮询次数越少、吞吐越高，但常驻堆内存也越多；内存紧张时可适当调小。
+    pub read_chunk_size: usize,
+    /// 请求体分块写入的块大小（字节），0 表示整体写入。
+    pub payload_chunk_size: usize,
+    /// 请求体分块之间的等待时间，用于避免高波特率下模块输入缓冲溢出；为 0 时不等待。
+    ///
+    /// 需明显小于模块的打包间隔时间，否则模块会把后续数据当作新的请求。
+    /// 紧邻 `command_mode_sequence` 的分块边界不等待，避免停顿构成转义所需的 guard time。
+    pub inter_chunk_delay: Duration,
+
+    // ── 日志 ──────────────────────────────────────────────────────────────────
+    /// 调试日志、诊断记录与 `AtObserver::on_command` 中脱敏 `Authorization` 头（Bearer Token 等），默认开启。
+    pub redact_secrets: bool,
+    /// 保留最近 AT 交互的条数（需 `diagnostics` feature），0 表示不记录。
+    #[cfg(feature = "diagnostics")]
+    pub recent_exchanges_len: usize,
+}
+
+impl Default for DtuAtHttpConfig {
+    fn default() -> Self {
+        Self {
+            channel: 1,
+            request_timeout_secs: 10,
+            response_filter_mask: 0x03,
+            require_body_on_success: true,
+            wait_for_full_body: false,
+            cmd_guard_time: Duration::from_millis(1200),
+            command_mode_sequence: b"+++",
+            at_first_timeout: Duration::from_secs(2),
+            at_idle_timeout: Duration::from_millis(250),
+            line_terminator: b"\r\n",
+            strip_command_echo: false,
+            ok_tokens: DEFAULT_OK_TOKENS,
+            error_tokens: DEFAULT_ERROR_TOKENS,
+            http_first_timeout: Duration::from_secs(60),
+            http_idle_timeout: Duration::from_millis(300),
+            http_followup_first_timeout: Duration::from_millis(700),
+            http_followup_timeout: Duration::from_secs(20),
+            http_ready_timeout: Duration::from_secs(25),
+            terminal_markers: DEFAULT_TERMINAL_MARKERS,
+            markers: Markers::DEFAULT,
+            enter_cmd_timeout: Duration::from_secs(60),
+            enter_cmd_poll: Duration::from_secs(2),
+            max_request_attempts: 2,
+            retry_backoff_base: Duration::from_ticks(0),
+            retry_backoff_max: Duration::from_secs(30),
+            retry_on: RetryPolicy::TransientOnly,
+            total_request_timeout: None,
+            enable_modem_debug_urc: false,
+            debug_urc_prefix: b"FS@DEBUG",
+            query_link_status_before_send: false,
+            min_signal_dbm: None,
+            retry_payload_on_http_timeout: false,
+            post_entm_settle_time: Duration::from_millis(500),
+            max_response_len: 4096,
+            response_capacity_hint: 512,
+            max_url_len: 256,
+            allow_any_scheme: false,
+            require_tls: false,
+            upgrade_insecure: false,
+            default_headers: &[],
+            auto_host_header: false,
+            auto_content_length: false,
+            split_long_headers: false,
+            follow_redirects: false,
+            max_redirects: 3,
+            read_chunk_size: 256,
+            payload_chunk_size: 0,
+            inter_chunk_delay: Duration::from_ticks(0),
+            redact_secrets: true,
+            #[cfg(feature = "diagnostics")]
+            recent_exchanges_len: 8,
+        }
+    }
+}
+
+impl DtuAtHttpConfig {
+    /// 从默认配置开始构造的链式 builder。
+    pub fn builder() -> DtuAtHttpConfigBuilder {
+        DtuAtHttpConfigBuilder::default()
+    }
+}
+
+/// [`DtuAtHttpConfig`] 的链式构造器，起始值为 `DtuAtHttpConfig::default()`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DtuAtHttpConfigBuilder {
+    config: DtuAtHttpConfig,
+}
+
+impl DtuAtHttpConfigBuilder {
+    /// 设置 [`DtuAtHttpConfig::channel`]。
+    pub const fn channel(mut self, channel: u8) -> Self {
+        self.config.channel = channel;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::request_timeout_secs`]。
+    pub const fn request_timeout_secs(mut self, request_timeout_secs: u16) -> Self {
+        self.config.request_timeout_secs = request_timeout_secs;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::response_filter_mask`]。
+    pub const fn response_filter_mask(mut self, response_filter_mask: u8) -> Self {
+        self.config.response_filter_mask = response_filter_mask;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::require_body_on_success`]。
+    pub const fn require_body_on_success(mut self, require_body_on_success: bool) -> Self {
+        self.config.require_body_on_success = require_body_on_success;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::wait_for_full_body`]。
+    pub const fn wait_for_full_body(mut self, wait_for_full_body: bool) -> Self {
+        self.config.wait_for_full_body = wait_for_full_body;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::cmd_guard_time`]。
+    pub const fn cmd_guard_time(mut self, cmd_guard_time: Duration) -> Self {
+        self.config.cmd_guard_time = cmd_guard_time;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::command_mode_sequence`]。
+    pub const fn command_mode_sequence(mut self, command_mode_sequence: &'static [u8]) -> Self {
+        self.config.command_mode_sequence = command_mode_sequence;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::at_first_timeout`]。
+    pub const fn at_first_timeout(mut self, at_first_timeout: Duration) -> Self {
+        self.config.at_first_timeout = at_first_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::at_idle_timeout`]。
+    pub const fn at_idle_timeout(mut self, at_idle_timeout: Duration) -> Self {
+        self.config.at_idle_timeout = at_idle_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::line_terminator`]。
+    pub const fn line_terminator(mut self, line_terminator: &'static [u8]) -> Self {
+        self.config.line_terminator = line_terminator;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::strip_command_echo`]。
+    pub const fn strip_command_echo(mut self, strip_command_echo: bool) -> Self {
+        self.config.strip_command_echo = strip_command_echo;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::ok_tokens`]。
+    pub const fn ok_tokens(mut self, ok_tokens: &'static [&'static [u8]]) -> Self {
+        self.config.ok_tokens = ok_tokens;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::error_tokens`]。
+    pub const fn error_tokens(mut self, error_tokens: &'static [&'static [u8]]) -> Self {
+        self.config.error_tokens = error_tokens;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::http_first_timeout`]。
+    pub const fn http_first_timeout(mut self, http_first_timeout: Duration) -> Self {
+        self.config.http_first_timeout = http_first_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::http_idle_timeout`]。
+    pub const fn http_idle_timeout(mut self, http_idle_timeout: Duration) -> Self {
+        self.config.http_idle_timeout = http_idle_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::http_followup_first_timeout`]。
+    pub const fn http_followup_first_timeout(
+        mut self,
+        http_followup_first_timeout: Duration,
+    ) -> Self {
+        self.config.http_followup_first_timeout = http_followup_first_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::http_followup_timeout`]。
+    pub const fn http_followup_timeout(mut self, http_followup_timeout: Duration) -> Self {
+        self.config.http_followup_timeout = http_followup_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::http_ready_timeout`]。
+    pub const fn http_ready_timeout(mut self, http_ready_timeout: Duration) -> Self {
+        self.config.http_ready_timeout = http_ready_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::terminal_markers`]。
+    pub const fn terminal_markers(mut self, terminal_markers: &'static [&'static [u8]]) -> Self {
+        self.config.terminal_markers = terminal_markers;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::markers`]。
+    pub const fn markers(mut self, markers: Markers) -> Self {
+        self.config.markers = markers;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::enter_cmd_timeout`]。
+    pub const fn enter_cmd_timeout(mut self, enter_cmd_timeout: Duration) -> Self {
+        self.config.enter_cmd_timeout = enter_cmd_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::enter_cmd_poll`]。
+    pub const fn enter_cmd_poll(mut self, enter_cmd_poll: Duration) -> Self {
+        self.config.enter_cmd_poll = enter_cmd_poll;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::max_request_attempts`]。
+    pub const fn max_request_attempts(mut self, max_request_attempts: u8) -> Self {
+        self.config.max_request_attempts = max_request_attempts;
+        self
+    }
 
-fn eq_ascii_case_prefix(line: &[u8], prefix: &[u8]) -> bool {
-    if line.len() < prefix.len() {
-        return false;
+    /// 设置 [`DtuAtHttpConfig::retry_backoff_base`]。
+    pub const fn retry_backoff_base(mut self, retry_backoff_base: Duration) -> Self {
+        self.config.retry_backoff_base = retry_backoff_base;
+        self
     }
-    for (a, b) in line[..prefix.len()].iter().zip(prefix.iter()) {
-        if a.to_ascii_lowercase() != b.to_ascii_lowercase() {
-            return false;
-        }
+
+    /// 设置 [`DtuAtHttpConfig::retry_backoff_max`]。
+    pub const fn retry_backoff_max(mut self, retry_backoff_max: Duration) -> Self {
+        self.config.retry_backoff_max = retry_backoff_max;
+        self
     }
-    true
-}
 
-/// DTU HTTP 客户端配置。
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct DtuAtHttpConfig {
-    // ── 业务参数 ──────────────────────────────────────────────────────────────
-    /// DTU 通道号（1~4），对应 AT+WKMOD{N}、AT+HTPURL{N} 等指令的编号。
-    pub channel: u8,
-    /// DTU 固件 HTTP 请求超时（秒），写入 AT+HTPTIM 指令。
-    pub request_timeout_secs: u16,
-    /// AT+HTPPK 响应过滤掩码（0x03 = 返回头+体）。
-    pub response_filter_mask: u8,
-    /// 成功响应（2xx）时要求必须有 body；204/304 豁免。
-    pub require_body_on_success: bool,
+    /// 设置 [`DtuAtHttpConfig::retry_on`]。
+    pub const fn retry_on(mut self, retry_on: RetryPolicy) -> Self {
+        self.config.retry_on = retry_on;
+        self
+    }
 
-    // ── AT 命令时序 ───────────────────────────────────────────────────────────
-    /// `+++` 前的静默时间（Hayes 规范要求 ≥1s），建议 ≥1200ms。
-    pub cmd_guard_time: Duration,
-    /// AT 命令等待第一个响应字节的超时。
-    pub at_first_timeout: Duration,
-    /// AT 命令收到首字节后的空闲超时（停止收集响应）。
-    pub at_idle_timeout: Duration,
+    /// 设置 [`DtuAtHttpConfig::total_request_timeout`]。
+    pub const fn total_request_timeout(mut self, total_request_timeout: Option<Duration>) -> Self {
+        self.config.total_request_timeout = total_request_timeout;
+        self
+    }
 
-    // ── HTTP 响应接收时序 ──────────────────────────────────────────────────────
-    /// 等待 HTTP 响应第一字节的超时（网络 RTT 较长时应增大）。
-    pub http_first_timeout: Duration,
-    /// HTTP 响应空闲超时（用于判断本次数据接收结束）。
-    pub http_idle_timeout: Duration,
-    /// `collect_followup` 轮询等待首字节的超时。
-    pub http_followup_first_timeout: Duration,
-    /// `collect_followup` 总时限。
-    pub http_followup_timeout: Duration,
-    /// 等待 `FS@HTTP OK:` 就绪信号的总时限。
-    pub http_ready_timeout: Duration,
+    /// 设置 [`DtuAtHttpConfig::enable_modem_debug_urc`]。
+    pub const fn enable_modem_debug_urc(mut self, enable_modem_debug_urc: bool) -> Self {
+        self.config.enable_modem_debug_urc = enable_modem_debug_urc;
+        self
+    }
 
-    // ── 命令模式进入与恢复 ─────────────────────────────────────────────────────
-    /// `enter_command_mode` 的总超时：覆盖从首次尝试到 DTU 重启恢复的全程。
-    /// 应 ≥ DTU AT+S 重启时间，建议 60s。
-    pub enter_cmd_timeout: Duration,
-    /// `wait_for_command_mode` 循环中每次 AT 探测的间隔。
-    pub enter_cmd_poll: Duration,
+    /// 设置 [`DtuAtHttpConfig::debug_urc_prefix`]。
+    pub const fn debug_urc_prefix(mut self, debug_urc_prefix: &'static [u8]) -> Self {
+        self.config.debug_urc_prefix = debug_urc_prefix;
+        self
+    }
 
-    // ── 请求级重试 ────────────────────────────────────────────────────────────
-    /// 单次请求失败后的最大重试总次数（含首次），≥1。
-    /// 用于 ESP32 重启但 DTU 未重启等导致 AT 命令偶发失败的场景。
-    pub max_request_attempts: u8,
+    /// 设置 [`DtuAtHttpConfig::query_link_status_before_send`]。
+    pub const fn query_link_status_before_send(
+        mut self,
+        query_link_status_before_send: bool,
+    ) -> Self {
+        self.config.query_link_status_before_send = query_link_status_before_send;
+        self
+    }
 
-    // ── 可选功能 ──────────────────────────────────────────────────────────────
-    /// 发送前开启 DTU 固件调试 URC（AT+DEBUG=ON）。
-    pub enable_modem_debug_urc: bool,
-    /// 发送前查询并打印链路状态（AT+CREG? / AT+RUNST?）。
-    pub query_link_status_before_send: bool,
-    /// HTTP 响应超时后重发一次 payload 再重试。
-    pub retry_payload_on_http_timeout: bool,
-    /// AT+S 后、进入透传模式前的额外等待时间。
-    pub post_entm_settle_time: Duration,
-    /// 单次请求允许的最大响应缓冲长度（字节）。
-    pub max_response_len: usize,
-}
+    /// 设置 [`DtuAtHttpConfig::min_signal_dbm`]。
+    pub const fn min_signal_dbm(mut self, min_signal_dbm: Option<i16>) -> Self {
+        self.config.min_signal_dbm = min_signal_dbm;
+        self
+    }
 
-impl Default for DtuAtHttpConfig {
-    fn default() -> Self {
-        Self {
-            channel: 1,
-            request_timeout_secs: 10,
-            response_filter_mask: 0x03,
-            require_body_on_success: true,
-            cmd_guard_time: Duration::from_millis(1200),
-            at_first_timeout: Duration::from_secs(2),
-            at_idle_timeout: Duration::from_millis(250),
-            http_first_timeout: Duration::from_secs(60),
-            http_idle_timeout: Duration::from_millis(300),
-            http_followup_first_timeout: Duration::from_millis(700),
-            http_followup_timeout: Duration::from_secs(20),
-            http_ready_timeout: Duration::from_secs(25),
-            enter_cmd_timeout: Duration::from_secs(60),
-            enter_cmd_poll: Duration::from_secs(2),
-            max_request_attempts: 2,
-            enable_modem_debug_urc: false,
-            query_link_status_before_send: false,
-            retry_payload_on_http_timeout: false,
-            post_entm_settle_time: Duration::from_millis(500),
-            max_response_len: 4096,
+    /// 设置 [`DtuAtHttpConfig::retry_payload_on_http_timeout`]。
+    pub const fn retry_payload_on_http_timeout(
+        mut self,
+        retry_payload_on_http_timeout: bool,
+    ) -> Self {
+        self.config.retry_payload_on_http_timeout = retry_payload_on_http_timeout;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::post_entm_settle_time`]。
+    pub const fn post_entm_settle_time(mut self, post_entm_settle_time: Duration) -> Self {
+        self.config.post_entm_settle_time = post_entm_settle_time;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::max_response_len`]。
+    pub const fn max_response_len(mut self, max_response_len: usize) -> Self {
+        self.config.max_response_len = max_response_len;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::response_capacity_hint`]。
+    pub const fn response_capacity_hint(mut self, response_capacity_hint: usize) -> Self {
+        self.config.response_capacity_hint = response_capacity_hint;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::max_url_len`]。
+    pub const fn max_url_len(mut self, max_url_len: usize) -> Self {
+        self.config.max_url_len = max_url_len;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::allow_any_scheme`]。
+    pub const fn allow_any_scheme(mut self, allow_any_scheme: bool) -> Self {
+        self.config.allow_any_scheme = allow_any_scheme;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::require_tls`]。
+    pub const fn require_tls(mut self, require_tls: bool) -> Self {
+        self.config.require_tls = require_tls;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::upgrade_insecure`]。
+    pub const fn upgrade_insecure(mut self, upgrade_insecure: bool) -> Self {
+        self.config.upgrade_insecure = upgrade_insecure;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::default_headers`]。
+    pub const fn default_headers(
+        mut self,
+        default_headers: &'static [HttpHeader<'static>],
+    ) -> Self {
+        self.config.default_headers = default_headers;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::auto_host_header`]。
+    pub const fn auto_host_header(mut self, auto_host_header: bool) -> Self {
+        self.config.auto_host_header = auto_host_header;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::auto_content_length`]。
+    pub const fn auto_content_length(mut self, auto_content_length: bool) -> Self {
+        self.config.auto_content_length = auto_content_length;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::split_long_headers`]。
+    pub const fn split_long_headers(mut self, split_long_headers: bool) -> Self {
+        self.config.split_long_headers = split_long_headers;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::follow_redirects`]。
+    pub const fn follow_redirects(mut self, follow_redirects: bool) -> Self {
+        self.config.follow_redirects = follow_redirects;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::max_redirects`]。
+    pub const fn max_redirects(mut self, max_redirects: u8) -> Self {
+        self.config.max_redirects = max_redirects;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::read_chunk_size`]。
+    pub const fn read_chunk_size(mut self, read_chunk_size: usize) -> Self {
+        self.config.read_chunk_size = read_chunk_size;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::payload_chunk_size`]。
+    pub const fn payload_chunk_size(mut self, payload_chunk_size: usize) -> Self {
+        self.config.payload_chunk_size = payload_chunk_size;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::inter_chunk_delay`]。
+    pub const fn inter_chunk_delay(mut self, inter_chunk_delay: Duration) -> Self {
+        self.config.inter_chunk_delay = inter_chunk_delay;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::redact_secrets`]。
+    pub const fn redact_secrets(mut self, redact_secrets: bool) -> Self {
+        self.config.redact_secrets = redact_secrets;
+        self
+    }
+
+    /// 设置 [`DtuAtHttpConfig::recent_exchanges_len`]。
+    #[cfg(feature = "diagnostics")]
+    pub const fn recent_exchanges_len(mut self, recent_exchanges_len: usize) -> Self {
+        self.config.recent_exchanges_len = recent_exchanges_len;
+        self
+    }
+
+    /// 校验并生成配置。
+    ///
+    /// `channel` 不在 1~4 或 `markers.urc_prefix` 为空时返回 `InvalidConfig`。
+    pub fn build(self) -> Result<DtuAtHttpConfig, DtuAtError> {
+        if !(1..=4).contains(&self.config.channel) {
+            return Err(DtuAtError::InvalidConfig("channel 必须在 1~4"));
         }
+        if self.config.markers.urc_prefix.is_empty() {
+            return Err(DtuAtError::InvalidConfig("markers.urc_prefix 不能为空"));
+        }
+        Ok(self.config)
     }
 }
 
+/// 驱动错误。
+///
+/// 标记为 `#[non_exhaustive]`：后续版本可能新增变体，外部 `match` 需保留 `_` 分支。
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
 pub enum DtuAtError {
-    Transport(IoError),
+    /// 传输层 IO 错误（携带 `embedded_io_async::ErrorKind`）。
+    ///
+    /// 只保留 `ErrorKind`，底层错误值本身被丢弃：保留原值需要让 `DtuAtError` 随传输层泛型化，
+    /// 并失去 `Copy` / `PartialEq`。需要原始错误时由传输层包装自行记录；`source()` 同样只能返回 `ErrorKind`。
+    Transport(ErrorKind),
     Timeout,
+    /// 模块上报 `FS@HTTP TIMEOUT`：模块侧等待服务器超时（区别于主机侧 UART 读取超时 `Timeout`）。
+    ModemTimeout,
     WriteZero,
     InvalidConfig(&'static str),
+    /// URL 为空、超长、含非法字符、scheme 不受支持或无法解析（缺少 scheme / host）。
+    InvalidUrl(&'static str),
     AtRejected,
+    /// 模块回复 `+CME ERROR: <n>`（设备 / 网络相关错误），携带错误码。
+    CmeError(u16),
+    /// 模块回复 `+CMS ERROR: <n>`（短消息相关错误），携带错误码。
+    CmsError(u16),
     BadResponse,
     ResponseTooLarge,
     BodyMissing,
+    /// 开启 `wait_for_full_body` 时，到期仍未收齐 `Content-Length` 声明的 body。
+    BodyTruncated {
+        have: usize,
+        want: usize,
+    },
     /// DTU 固件级 HTTP 失败（FS@HTTP FAIL:N），通常为 TLS/连接层错误。
-    /// 携带 DTU 返回的错误码（0 表示未解析到）。
+    /// 携带 DTU 返回的原始错误码（0 表示未解析到）；固件手册未给出各错误码的含义，因此不做映射。
     HttpFail(u8),
+    /// 固件拒绝了 `AT+HTPTP` 中的请求方法（固件不支持该方法）。
+    UnsupportedMethod,
+    /// JSON 序列化 / 反序列化失败（`serde` feature）。
+    Serde(&'static str),
+    /// 自动跟随重定向超过 `max_redirects` 跳。
+    TooManyRedirects,
+    /// 发送前测得的信号强度（dBm）低于 `min_signal_dbm`。
+    WeakSignal(i16),
+    /// 请求体以命令模式转义序列（`command_mode_sequence`）开头或结尾，发送可能让模块中途切回命令模式。
+    PayloadContainsEscape,
+    /// 开启 `require_tls` 时请求（或重定向目标）为明文 `http://`。
+    TlsRequired,
 }
 
 impl DtuAtError {
@@ -429,13 +1991,521 @@ impl DtuAtError {
         match self {
             Self::Transport(_) => "transport error",
             Self::Timeout => "timeout",
+            Self::ModemTimeout => "DTU HTTP TIMEOUT (server did not respond)",
             Self::WriteZero => "write returned zero",
             Self::InvalidConfig(msg) => msg,
+            Self::InvalidUrl(msg) => msg,
             Self::AtRejected => "AT rejected (ERR/ERROR)",
+            Self::CmeError(_) => "AT rejected (+CME ERROR)",
+            Self::CmsError(_) => "AT rejected (+CMS ERROR)",
             Self::BadResponse => "AT response missing OK",
             Self::ResponseTooLarge => "response too large",
             Self::BodyMissing => "http body missing",
+            Self::BodyTruncated { .. } => "http body truncated",
             Self::HttpFail(_) => "DTU HTTP FAIL (TLS/connection error)",
+            Self::UnsupportedMethod => "http method not supported by firmware",
+            Self::Serde(msg) => msg,
+            Self::TooManyRedirects => "too many redirects",
+            Self::WeakSignal(_) => "signal below min_signal_dbm",
+            Self::PayloadContainsEscape => "payload starts or ends with command-mode sequence",
+            Self::TlsRequired => "plaintext http rejected by require_tls",
+        }
+    }
+
+    /// 传输层错误的 `ErrorKind`（仅 `Transport` 变体）。
+    pub fn transport_kind(&self) -> Option<ErrorKind> {
+        match self {
+            Self::Transport(kind) => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for DtuAtError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Transport(kind) => write!(f, "{}: {}", self.as_str(), kind),
+            Self::HttpFail(code) => write!(f, "{}, code={}", self.as_str(), code),
+            Self::CmeError(code) | Self::CmsError(code) => {
+                write!(f, "{}, code={}", self.as_str(), code)
+            }
+            Self::WeakSignal(dbm) => write!(f, "{}, rssi={}dBm", self.as_str(), dbm),
+            Self::BodyTruncated { have, want } => {
+                write!(f, "{}, have={} want={}", self.as_str(), have, want)
+            }
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+/// `core::error::Error` 与 `std::error::Error` 是同一 trait，`no_std` 下同样可用。
+impl core::error::Error for DtuAtError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Transport(kind) => Some(kind),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(raw: &[u8]) -> HttpResponse {
+        HttpResponse {
+            status_code: Some(200),
+            raw: raw.to_vec(),
+            markers: Markers::DEFAULT,
+        }
+    }
+
+    #[cfg(feature = "decompress")]
+    const GZIP_HELLO: [u8; 28] = [
+        0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xCB, 0x48, 0xCD, 0xC9, 0xC9,
+        0x57, 0xC8, 0x40, 0x27, 0x01, 0xE3, 0x51, 0x3D, 0x8D, 0x17, 0x00, 0x00, 0x00,
+    ];
+
+    #[cfg(feature = "decompress")]
+    fn gzip_response() -> HttpResponse {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        raw.extend_from_slice(&GZIP_HELLO);
+        response(&raw)
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn gzip_body_is_inflated() {
+        let body = gzip_response().decoded_body_with_encoding(64).unwrap();
+        assert_eq!(body, b"hello hello hello hello");
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn gzip_body_over_limit_is_rejected() {
+        let err = gzip_response().decoded_body_with_encoding(8).unwrap_err();
+        assert_eq!(err, DtuAtError::ResponseTooLarge);
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn deflate_body_over_limit_is_rejected() {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Encoding: deflate\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[
+            0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x27, 0x01, 0x68, 0x03,
+            0x08, 0xB1,
+        ]);
+        let resp = response(&raw);
+        assert_eq!(
+            resp.decoded_body_with_encoding(64).unwrap(),
+            b"hello hello hello hello"
+        );
+        assert_eq!(
+            resp.decoded_body_with_encoding(8).unwrap_err(),
+            DtuAtError::ResponseTooLarge
+        );
+    }
+
+    #[test]
+    fn identity_body_respects_limit() {
+        let resp = response(b"HTTP/1.1 200 OK\r\n\r\nhello");
+        assert_eq!(resp.decoded_body_with_encoding(5).unwrap(), b"hello");
+        assert_eq!(
+            resp.decoded_body_with_encoding(4).unwrap_err(),
+            DtuAtError::ResponseTooLarge
+        );
+    }
+
+    #[test]
+    fn content_length_end_needs_complete_header() {
+        let raw =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello\r\nFS@HTTP SUCCESS CODE:200\r\n";
+        let end = content_length_end(raw).unwrap();
+        assert_eq!(
+            &raw[..end],
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello"
+        );
+        // 头块未结束时无法判定
+        assert_eq!(
+            content_length_end(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn content_length_end_falls_back_without_usable_length() {
+        assert_eq!(content_length_end(b"HTTP/1.1 200 OK\r\n\r\nhello"), None);
+        assert_eq!(
+            content_length_end(b"HTTP/1.1 200 OK\r\nContent-Length: abc\r\n\r\nhello"),
+            None
+        );
+        assert_eq!(
+            content_length_end(
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n5\r\nhello\r\n0\r\n\r\n"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn body_follows_final_status_line() {
+        let resp = response(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi",
+        );
+        assert_eq!(resp.http_body(), Some(&b"hi"[..]));
+        assert_eq!(resp.declared_content_length(), Some(2));
+    }
+
+    #[test]
+    fn parses_content_range() {
+        let resp = response(
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-1023/4096\r\nContent-Length: 1024\r\n\r\n",
+        );
+        assert_eq!(resp.content_range(), Some((0, 1023, Some(4096))));
+
+        let resp =
+            response(b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 10-19/*\r\n\r\n");
+        assert_eq!(resp.content_range(), Some((10, 19, None)));
+
+        let resp = response(b"HTTP/1.1 206 Partial Content\r\nContent-Range: items 0-1/2\r\n\r\n");
+        assert_eq!(resp.content_range(), None);
+    }
+
+    #[test]
+    fn http_body_uses_stored_markers() {
+        let raw = b"HTTP/1.1 204 No Content\r\n\r\nXX@HTTP SUCCESS CODE:204\r\n";
+        let mut resp = response(raw);
+        assert!(resp.http_body().is_some());
+
+        resp.markers = Markers {
+            urc_prefix: b"XX@",
+            ..Markers::DEFAULT
+        };
+        assert_eq!(resp.http_body(), None);
+    }
+
+    #[test]
+    fn headers_skip_configured_urc_prefix() {
+        let mut resp = response(b"HTTP/1.1 200 OK\r\nXX@HTTP INFO:1\r\nETag: \"v1\"\r\n\r\nhi");
+        resp.markers = Markers {
+            urc_prefix: b"XX@",
+            ..Markers::DEFAULT
+        };
+        assert_eq!(
+            resp.headers(),
+            [(String::from("ETag"), String::from("\"v1\""))]
+        );
+        assert_eq!(resp.header("XX@HTTP INFO"), None);
+        assert_eq!(resp.header("ETag"), Some("\"v1\""));
+    }
+
+    #[test]
+    fn build_url_strips_fragment_before_query() {
+        let req = HttpRequest::new(HttpMethod::Get, "http://example.com/p?a=1#top")
+            .with_encoded_query(&[("b", "x y")]);
+        assert_eq!(req.build_url(), "http://example.com/p?a=1&b=x%20y");
+    }
+
+    #[test]
+    fn method_at_names_round_trip() {
+        for (method, name) in [
+            (HttpMethod::Get, "GET"),
+            (HttpMethod::Post, "POST"),
+            (HttpMethod::Put, "PUT"),
+            (HttpMethod::Patch, "PATCH"),
+            (HttpMethod::Delete, "DELETE"),
+            (HttpMethod::Head, "HEAD"),
+        ] {
+            assert_eq!(method.as_at(), name);
+            assert_eq!(HttpMethod::from_at(name), Some(method));
+        }
+        assert_eq!(HttpMethod::from_at("post"), Some(HttpMethod::Post));
+        assert_eq!(HttpMethod::from_at("OPTIONS"), None);
+    }
+
+    #[test]
+    fn headers_parse_crlf_and_bare_lf() {
+        let expected = [
+            (String::from("A"), String::from("1")),
+            (String::from("B"), String::from("two")),
+        ];
+        let crlf = response(b"HTTP/1.1 200 OK\r\nA: 1\r\nB:  two \r\n\r\nbody");
+        assert_eq!(crlf.headers(), expected);
+        let lf = response(b"HTTP/1.1 200 OK\nA: 1\nB:  two \n\nbody");
+        assert_eq!(lf.headers(), expected);
+        assert_eq!(lf.header("B"), Some("two"));
+        assert_eq!(lf.http_body(), Some(&b"body"[..]));
+    }
+
+    #[test]
+    fn duplicate_headers_match_case_insensitively() {
+        let resp = response(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nset-cookie: b=2\r\nSET-COOKIE: c=3\r\n\r\n",
+        );
+        assert_eq!(resp.header_values("Set-Cookie"), ["a=1", "b=2", "c=3"]);
+        assert_eq!(resp.header_value("SET-cookie").as_deref(), Some("a=1"));
+        // header() 区分大小写
+        assert_eq!(resp.header("set-cookie"), Some("b=2"));
+        assert_eq!(resp.header("Set-cookie"), None);
+    }
+
+    #[test]
+    fn decodes_chunked_body() {
+        const HEAD: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let chunked = |body: &[u8]| {
+            let mut raw = HEAD.to_vec();
+            raw.extend_from_slice(body);
+            response(&raw).decoded_body().unwrap()
+        };
+
+        assert_eq!(
+            chunked(b"5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\n\r\nFS@HTTP OK:1\r\n"),
+            b"hello world"
+        );
+        // 长度行不是十六进制：停在已解码的部分
+        assert_eq!(
+            chunked(b"5\r\nhello\r\nzz\r\n world\r\n0\r\n\r\n"),
+            b"hello"
+        );
+        // 缺少终止块：返回已收到的全部分块
+        assert_eq!(chunked(b"5\r\nhello\r\n6\r\n wor"), b"hello wor");
+    }
+
+    #[test]
+    fn builder_matches_struct_literal() {
+        let built = DtuAtHttpConfig::builder()
+            .channel(2)
+            .request_timeout_secs(30)
+            .cmd_guard_time(Duration::from_millis(1500))
+            .max_request_attempts(3)
+            .follow_redirects(true)
+            .max_redirects(4)
+            .min_signal_dbm(Some(-100))
+            .build()
+            .unwrap();
+        let literal = DtuAtHttpConfig {
+            channel: 2,
+            request_timeout_secs: 30,
+            cmd_guard_time: Duration::from_millis(1500),
+            max_request_attempts: 3,
+            follow_redirects: true,
+            max_redirects: 4,
+            min_signal_dbm: Some(-100),
+            ..DtuAtHttpConfig::default()
+        };
+        assert_eq!(built, literal);
+        assert_eq!(
+            DtuAtHttpConfig::builder().build().unwrap(),
+            DtuAtHttpConfig::default()
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_config() {
+        for channel in [0, 5] {
+            assert!(matches!(
+                DtuAtHttpConfig::builder().channel(channel).build(),
+                Err(DtuAtError::InvalidConfig(_))
+            ));
+        }
+        let markers = Markers {
+            urc_prefix: b"",
+            ..Markers::DEFAULT
+        };
+        assert!(matches!(
+            DtuAtHttpConfig::builder().markers(markers).build(),
+            Err(DtuAtError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn response_display_shows_code_length_and_preview() {
+        let resp = response(b"HTTP/1.1 200 OK\r\n\r\nhi");
+        assert_eq!(
+            alloc::format!("{resp}"),
+            "HTTP 200 (21 bytes): HTTP/1.1 200 OK\r\n\r\nhi"
+        );
+
+        let mut raw = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+        raw.resize(RESPONSE_PREVIEW_LEN + 40, b'x');
+        let shown = alloc::format!("{}", response(&raw));
+        assert!(shown.starts_with("HTTP 200 (200 bytes): "));
+        assert_eq!(
+            shown.len(),
+            "HTTP 200 (200 bytes): ".len() + RESPONSE_PREVIEW_LEN
+        );
+    }
+
+    #[test]
+    fn error_display_includes_codes() {
+        let cases = [
+            (
+                DtuAtError::HttpFail(3),
+                "DTU HTTP FAIL (TLS/connection error), code=3",
+            ),
+            (
+                DtuAtError::CmeError(30),
+                "AT rejected (+CME ERROR), code=30",
+            ),
+            (
+                DtuAtError::CmsError(500),
+                "AT rejected (+CMS ERROR), code=500",
+            ),
+            (
+                DtuAtError::WeakSignal(-105),
+                "signal below min_signal_dbm, rssi=-105dBm",
+            ),
+            (
+                DtuAtError::BodyTruncated { have: 5, want: 10 },
+                "http body truncated, have=5 want=10",
+            ),
+            (DtuAtError::Timeout, "timeout"),
+        ];
+        for (err, text) in cases {
+            assert_eq!(alloc::format!("{err}"), text);
         }
     }
+
+    #[test]
+    fn location_with_crlf_and_lf() {
+        let crlf = response(b"HTTP/1.1 302 Found\r\nLocation: /next?a=1 \r\n\r\n");
+        assert_eq!(crlf.location().as_deref(), Some("/next?a=1"));
+        let lf = response(b"HTTP/1.1 301 Moved\nlocation: https://example.com/x\n\n");
+        assert_eq!(lf.location().as_deref(), Some("https://example.com/x"));
+        assert_eq!(
+            response(b"HTTP/1.1 302 Found\r\nLocation:\r\n\r\n").location(),
+            None
+        );
+        assert_eq!(response(b"\r\nFS@HTTP 302\r\n").location(), None);
+    }
+
+    #[test]
+    fn content_type_splits_charset() {
+        let resp =
+            response(b"HTTP/1.1 200 OK\r\nContent-Type: text/html; q=1; CHARSET=\"UTF-8\"\r\n\r\n");
+        assert_eq!(
+            resp.content_type(),
+            Some((String::from("text/html"), Some(String::from("UTF-8"))))
+        );
+        let resp = response(b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\r\n{}");
+        assert_eq!(
+            resp.content_type(),
+            Some((String::from("application/json"), None))
+        );
+        assert_eq!(response(b"HTTP/1.1 200 OK\r\n\r\n").content_type(), None);
+    }
+
+    #[test]
+    fn text_decodes_by_charset() {
+        let latin1 = response(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=ISO-8859-1\r\n\r\ncaf\xE9",
+        );
+        assert_eq!(latin1.text(), "caf\u{E9}");
+
+        let utf8 = response(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n\r\ncaf\xC3\xA9",
+        );
+        assert_eq!(utf8.text(), "caf\u{E9}");
+
+        // 未声明 charset：按 UTF-8 宽松解码
+        let undeclared = response(b"HTTP/1.1 200 OK\r\n\r\ncaf\xE9");
+        assert_eq!(undeclared.text(), "caf\u{FFFD}");
+    }
+
+    #[test]
+    fn cookies_drop_attributes() {
+        let resp = response(
+            b"HTTP/1.1 200 OK\r\n\
+Set-Cookie: sid=abc123; Path=/; HttpOnly; Max-Age=3600\r\n\
+set-cookie: theme = dark ; Secure\r\n\
+Set-Cookie: novalue\r\n\
+Set-Cookie: =orphan\r\n\r\n",
+        );
+        assert_eq!(
+            resp.cookies(),
+            [
+                (String::from("sid"), String::from("abc123")),
+                (String::from("theme"), String::from("dark")),
+            ]
+        );
+    }
+
+    #[test]
+    fn status_class_boundaries() {
+        let class = |code| {
+            HttpResponse {
+                status_code: code,
+                raw: Vec::new(),
+                markers: Markers::DEFAULT,
+            }
+            .status_class()
+        };
+        assert_eq!(class(Some(99)), None);
+        assert_eq!(class(Some(100)), Some(StatusClass::Informational));
+        assert_eq!(class(Some(199)), Some(StatusClass::Informational));
+        assert_eq!(class(Some(200)), Some(StatusClass::Success));
+        assert_eq!(class(Some(299)), Some(StatusClass::Success));
+        assert_eq!(class(Some(300)), Some(StatusClass::Redirect));
+        assert_eq!(class(Some(399)), Some(StatusClass::Redirect));
+        assert_eq!(class(Some(400)), Some(StatusClass::ClientError));
+        assert_eq!(class(Some(499)), Some(StatusClass::ClientError));
+        assert_eq!(class(Some(500)), Some(StatusClass::ServerError));
+        assert_eq!(class(Some(599)), Some(StatusClass::ServerError));
+        assert_eq!(class(Some(600)), None);
+        assert_eq!(class(None), None);
+    }
+
+    #[test]
+    fn status_reason_phrases() {
+        let reason = |raw: &[u8]| response(raw).status_reason();
+        assert_eq!(reason(b"HTTP/1.1 200 OK\r\n\r\n").as_deref(), Some("OK"));
+        assert_eq!(
+            reason(b"HTTP/1.1 503 Service Temporarily Unavailable \r\n\r\n").as_deref(),
+            Some("Service Temporarily Unavailable")
+        );
+        assert_eq!(
+            reason(b"HTTP/1.1 404 Not Found\n\n").as_deref(),
+            Some("Not Found")
+        );
+        // 原因短语为空或缺失
+        assert_eq!(reason(b"HTTP/1.1 204 \r\n\r\n"), None);
+        assert_eq!(reason(b"HTTP/1.1 204\r\n\r\n"), None);
+        // 只有结果 URC
+        assert_eq!(reason(b"\r\nFS@HTTP SUCCESS CODE:200\r\n"), None);
+    }
+
+    #[test]
+    fn body_length_checked_against_content_length() {
+        let full = response(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        assert_eq!(full.body_matches_content_length(), Some(true));
+
+        let short = response(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello");
+        assert_eq!(short.declared_content_length(), Some(10));
+        assert_eq!(short.body_matches_content_length(), Some(false));
+
+        let undeclared = response(b"HTTP/1.1 200 OK\r\n\r\nhello");
+        assert_eq!(undeclared.body_matches_content_length(), None);
+    }
+
+    #[test]
+    fn sorted_query_ignores_input_order() {
+        let url = |pairs: &[(&str, &str)]| {
+            HttpRequest::new(HttpMethod::Get, "http://example.com/sign")
+                .with_sorted_query(pairs)
+                .build_url()
+        };
+        let expected = "http://example.com/sign?a=1&b=2&b=3&ts=9";
+        assert_eq!(
+            url(&[("ts", "9"), ("b", "2"), ("a", "1"), ("b", "3")]),
+            expected
+        );
+        assert_eq!(
+            url(&[("a", "1"), ("b", "2"), ("ts", "9"), ("b", "3")]),
+            expected
+        );
+        assert_eq!(
+            url(&[("b", "2"), ("b", "3"), ("ts", "9"), ("a", "1")]),
+            expected
+        );
+    }
 }
@@ -1,3 +1,7 @@
+use alloc::string::String;
+
+use core::fmt::Write as _;
+
 pub(crate) fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
     if needle.is_empty() || haystack.len() < needle.len() {
         return None;
@@ -7,3 +11,318 @@ pub(crate) fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
         .windows(needle.len())
         .position(|window| window == needle)
 }
+
+/// 最终状态行 `HTTP/1.x NNN ...` 的位置：跳过 1xx 临时响应（如 `100 Continue`）；
+/// 只有 1xx 时返回最后一个。
+pub(crate) fn find_final_status_line(raw: &[u8]) -> Option<usize> {
+    let mut pos = 0usize;
+    let mut last = None;
+    while let Some(rel) = find_subslice(&raw[pos..], b"HTTP/1.") {
+        let idx = pos + rel;
+        if !is_informational_status(&raw[idx..]) {
+            return Some(idx);
+        }
+        last = Some(idx);
+        pos = idx + 1;
+    }
+    last
+}
+
+/// 状态行的状态码是否为 1xx。
+fn is_informational_status(line: &[u8]) -> bool {
+    let line_end = line
+        .iter()
+        .position(|b| *b == b'\r' || *b == b'\n')
+        .unwrap_or(line.len());
+    let line = &line[..line_end];
+    let Some(space) = line.iter().position(|b| *b == b' ') else {
+        return false;
+    };
+    let code = &line[space + 1..];
+    code.len() >= 3
+        && code[0] == b'1'
+        && code[..3].iter().all(u8::is_ascii_digit)
+        && !code.get(3).is_some_and(u8::is_ascii_digit)
+}
+
+/// 大小写不敏感（仅 ASCII）的子串查找。
+pub(crate) fn find_ascii_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// 百分号编码：非保留字符（`A-Za-z0-9-._~`）原样输出，其余字节编码为 `%XX`。
+pub(crate) fn percent_encode(input: &str, out: &mut String) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    for &b in input.as_bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push('%');
+            out.push(HEX[(b >> 4) as usize] as char);
+            out.push(HEX[(b & 0x0F) as usize] as char);
+        }
+    }
+}
+
+/// 标准 Base64 编码（RFC 4648 字母表，带 `=` 填充）。
+pub(crate) fn base64_encode(input: &[u8], out: &mut String) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// 将键值对编码为 `k=v&k2=v2`（键与值分别百分号编码）。
+///
+/// 按传入顺序逐对输出，重复的键原样保留（如 `id=1&id=2&id=3`），用于数组形式的参数。
+pub(crate) fn encode_query_pairs(pairs: &[(&str, &str)], out: &mut String) {
+    for (idx, (key, value)) in pairs.iter().enumerate() {
+        if idx > 0 {
+            out.push('&');
+        }
+        percent_encode(key, out);
+        out.push('=');
+        percent_encode(value, out);
+    }
+}
+
+/// URL 各组成部分（借用原字符串）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UrlParts<'a> {
+    pub scheme: &'a str,
+    pub userinfo: Option<&'a str>,
+    /// 主机名；IPv6 字面量不含方括号。
+    pub host: &'a str,
+    pub port: Option<u16>,
+    /// 路径，URL 中缺省时为空串。
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+    pub fragment: Option<&'a str>,
+}
+
+/// 拆分 `scheme://[userinfo@]host[:port][/path][?query][#fragment]` 形式的 URL。
+///
+/// 并非完整的 RFC 3986 实现，仅覆盖模块接受的 http / https URL；
+/// 缺少 scheme、host 为空、端口非法或 IPv6 方括号不闭合时返回 `None`。
+pub(crate) fn parse_url(url: &str) -> Option<UrlParts<'_>> {
+    let (scheme, rest) = url.split_once("://")?;
+    if scheme.is_empty()
+        || !scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+    {
+        return None;
+    }
+
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, rest) = rest.split_at(authority_end);
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((userinfo, host_port)) => (Some(userinfo), host_port),
+        None => (None, authority),
+    };
+
+    let (host, port) = if let Some(literal) = host_port.strip_prefix('[') {
+        let (host, after) = literal.split_once(']')?;
+        match after {
+            "" => (host, None),
+            _ => (host, Some(after.strip_prefix(':')?)),
+        }
+    } else {
+        match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = match port {
+        None | Some("") => None,
+        Some(port) => Some(port.parse().ok()?),
+    };
+
+    let (rest, fragment) = match rest.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (rest, None),
+    };
+    let (path, query) = match rest.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (rest, None),
+    };
+
+    Some(UrlParts {
+        scheme,
+        userinfo,
+        host,
+        port,
+        path,
+        query,
+        fragment,
+    })
+}
+
+/// 由 URL 生成 `Host` 头的值：去除 `userinfo@`，保留端口，IPv6 字面量加方括号。
+pub(crate) fn host_header_value(url: &str) -> Option<String> {
+    let parts = parse_url(url)?;
+    let mut out = String::new();
+    if parts.host.contains(':') {
+        out.push('[');
+        out.push_str(parts.host);
+        out.push(']');
+    } else {
+        out.push_str(parts.host);
+    }
+    if let Some(port) = parts.port {
+        let _ = write!(out, ":{}", port);
+    }
+    Some(out)
+}
+
+/// 按 `base` 解析重定向 `Location`：绝对 URL 原样返回，`//host/...` 沿用 scheme，
+/// `/path` 沿用 scheme 与 host，其余按 `base` 所在目录拼接。
+pub(crate) fn resolve_location(base: &str, location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return String::from(location);
+    }
+
+    let scheme_end = base.find("://").map_or(0, |idx| idx + 3);
+    let (scheme, rest) = base.split_at(scheme_end);
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+
+    let mut out = String::new();
+    if let Some(net_path) = location.strip_prefix("//") {
+        out.push_str(scheme);
+        out.push_str(net_path);
+    } else if location.starts_with('/') {
+        out.push_str(scheme);
+        out.push_str(authority);
+        out.push_str(location);
+    } else {
+        let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+        out.push_str(scheme);
+        out.push_str(authority);
+        out.push_str(&path[..path.rfind('/').map_or(0, |idx| idx + 1)]);
+        if !out.ends_with('/') {
+            out.push('/');
+        }
+        out.push_str(location);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_known_answer() {
+        let mut out = String::new();
+        base64_encode(b"aladdin:opensesame", &mut out);
+        assert_eq!(out, "YWxhZGRpbjpvcGVuc2VzYW1l");
+    }
+
+    #[test]
+    fn parses_ipv6_literal() {
+        let parts = parse_url("http://[::1]:80/x").unwrap();
+        assert_eq!(parts.scheme, "http");
+        assert_eq!(parts.host, "::1");
+        assert_eq!(parts.port, Some(80));
+        assert_eq!(parts.path, "/x");
+        assert_eq!(parts.query, None);
+    }
+
+    #[test]
+    fn missing_path_is_empty() {
+        let parts = parse_url("https://user:pw@example.com:8443").unwrap();
+        assert_eq!(parts.userinfo, Some("user:pw"));
+        assert_eq!(parts.host, "example.com");
+        assert_eq!(parts.port, Some(8443));
+        assert_eq!(parts.path, "");
+
+        let parts = parse_url("http://example.com?a=1").unwrap();
+        assert_eq!(parts.path, "");
+        assert_eq!(parts.query, Some("a=1"));
+    }
+
+    #[test]
+    fn splits_query_and_fragment() {
+        let parts = parse_url("http://example.com/a/b?x=1&y=2#top").unwrap();
+        assert_eq!(parts.path, "/a/b");
+        assert_eq!(parts.query, Some("x=1&y=2"));
+        assert_eq!(parts.fragment, Some("top"));
+
+        // 片段中的 `?` 不属于查询串
+        let parts = parse_url("http://example.com/p#frag?no").unwrap();
+        assert_eq!(parts.query, None);
+        assert_eq!(parts.fragment, Some("frag?no"));
+    }
+
+    #[test]
+    fn rejects_malformed_urls() {
+        assert_eq!(parse_url("example.com/x"), None);
+        assert_eq!(parse_url("://example.com/"), None);
+        assert_eq!(parse_url("http:///x"), None);
+        assert_eq!(parse_url("http://[::1/x"), None);
+        assert_eq!(parse_url("http://example.com:99999/"), None);
+    }
+
+    #[test]
+    fn host_header_keeps_only_explicit_port() {
+        assert_eq!(
+            host_header_value("http://example.com/x").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            host_header_value("https://user:pw@example.com:8443").as_deref(),
+            Some("example.com:8443")
+        );
+        assert_eq!(
+            host_header_value("http://[fe80::1]:8080/").as_deref(),
+            Some("[fe80::1]:8080")
+        );
+        assert_eq!(host_header_value("http://[::1]").as_deref(), Some("[::1]"));
+    }
+
+    #[test]
+    fn host_header_rejects_url_without_scheme() {
+        assert_eq!(host_header_value("example.com/x"), None);
+        assert_eq!(host_header_value("ht tp://example.com/"), None);
+    }
+
+    #[test]
+    fn percent_encodes_reserved_and_multibyte() {
+        let mut out = String::new();
+        percent_encode("a b&c=d/~-._", &mut out);
+        assert_eq!(out, "a%20b%26c%3Dd%2F~-._");
+
+        out.clear();
+        percent_encode("温度", &mut out);
+        assert_eq!(out, "%E6%B8%A9%E5%BA%A6");
+    }
+
+    #[test]
+    fn repeated_query_keys_keep_order() {
+        let mut out = String::new();
+        encode_query_pairs(&[("id", "1"), ("id", "2"), ("id", "3")], &mut out);
+        assert_eq!(out, "id=1&id=2&id=3");
+    }
+}